@@ -1,9 +1,30 @@
+use anyhow::ensure;
 use rand::prelude::*;
-use std::{collections::HashMap, usize};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use crate::logic::{Board, CellState};
+use crate::{
+    checkpoint::{interrupted, load_json, map_as_entries, save_json, CheckpointConfig},
+    convergence::{ConvergenceTracker, EarlyStopping, EpochChange, StopReason},
+    dqn::{train_dqn, DqnConfig},
+    evaluation::evaluate,
+    experiment::ExperimentConfig,
+    inspect::CellStats,
+    league::{League, LeaguePool},
+    logic::{Board, BoardConfig, CellState},
+    mcts::{Budget, Mcts, Rollout},
+    minimax::MinimaxAgent,
+    policy::{Exploration, Policy},
+    replay::{ReplayBuffer, ReplayConfig, Transition},
+};
 
-struct Reward;
+const MCTS_EXPLORATION: f64 = 1.4;
+
+pub(crate) struct Reward;
 
 impl Reward {
     pub const WIN: i32 = 2;
@@ -12,45 +33,393 @@ impl Reward {
     pub const INTERMEDIATE: i32 = 0; // Repeated values are fine in this context
 }
 
-#[derive(Hash, Eq, Clone, PartialEq, Copy, Debug)]
+#[derive(Hash, Eq, Clone, PartialEq, Copy, Debug, Serialize, Deserialize)]
 pub struct Action {
     pub x_axis: usize,
     pub y_axis: usize,
+    /// Layer of 3D games or board of multi-board games, always 0 on a single board
+    #[serde(default)]
+    pub layer: usize,
+    /// Symbol placed in the wild variant, the one of the player to move when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<CellState>,
+}
+
+impl Action {
+    /// Lists the legal moves of the player to move on the board
+    pub fn possible_actions(board: &Board) -> Vec<Action> {
+        board
+            .get_possible_actions()
+            .iter()
+            .map(|&(x_axis, y_axis)| Action {
+                x_axis,
+                y_axis,
+                layer: 0,
+                symbol: None,
+            })
+            .collect()
+    }
+}
+
+/// Anything able to choose a move for the player to move on a board, all randomness is drawn
+/// from `rng` so seeded runs are reproducible
+pub trait Agent {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action;
+
+    /// Estimated value of every legal move for the player to move, for agents that have one
+    fn action_values(&self, _board: &Board) -> Option<Vec<(Action, f64)>> {
+        None
+    }
+}
+
+/// Baseline agent playing uniformly among the legal moves
+pub struct RandomAgent;
+
+impl Agent for RandomAgent {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        *Action::possible_actions(board).choose(rng).unwrap()
+    }
+}
+
+/// Agent completing its own lines first, then blocking the opponent, and playing randomly
+/// otherwise, never picking a move losing on the spot when another one is left
+pub struct HeuristicAgent;
+
+impl Agent for HeuristicAgent {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        let possible_actions = Action::possible_actions(board);
+        let player = board.get_current_player();
+        let completes_line = |action: &&Action, player: CellState| {
+            let mut board = board.clone();
+            board.set_current_player(player);
+            board.play_move(action.x_axis, action.y_axis);
+            board.is_winner() == Some(player)
+        };
+        let loses = |action: &&Action| {
+            let mut board = board.clone();
+            board.play_move(action.x_axis, action.y_axis);
+            board.is_winner() == Some(player.opponent())
+        };
+        // In the misère variant completing a line loses, so only safe moves are kept
+        let safe_actions: Vec<Action> = possible_actions
+            .iter()
+            .filter(|action| !loses(action))
+            .copied()
+            .collect();
+        let possible_actions = if safe_actions.is_empty() {
+            possible_actions
+        } else {
+            safe_actions
+        };
+        possible_actions
+            .iter()
+            .find(|action| completes_line(action, player))
+            .or_else(|| {
+                possible_actions
+                    .iter()
+                    .find(|action| completes_line(action, player.opponent()))
+            })
+            .copied()
+            .unwrap_or_else(|| *possible_actions.choose(rng).unwrap())
+    }
+}
+
+/// Description of an agent, built once the board is known since learners train on it
+#[derive(Clone, Debug)]
+pub enum AgentSpec {
+    Random,
+    Heuristic,
+    QTable {
+        n_games: usize,
+        n_epoch: usize,
+        options: Box<TrainingOptions>,
+        /// Policy followed once trained
+        policy: Policy,
+    },
+    /// Q-table trained and saved by an earlier run
+    SavedQTable {
+        path: PathBuf,
+        policy: Policy,
+    },
+    Dqn {
+        n_games: usize,
+        config: DqnConfig,
+        policy: Policy,
+    },
+    Mcts {
+        budget: Budget,
+        heuristic_rollouts: bool,
+    },
+    Minimax {
+        depth: usize,
+    },
+}
+
+impl AgentSpec {
+    /// Makes the learners train from `start` instead of the empty board
+    pub fn with_start(mut self, start: Option<String>) -> Self {
+        match &mut self {
+            AgentSpec::QTable { options, .. } => options.start = start,
+            AgentSpec::Dqn { config, .. } => config.start = start,
+            _ => {}
+        }
+        self
+    }
+
+    pub fn build(
+        &self,
+        board: BoardConfig,
+        rng: &mut dyn RngCore,
+    ) -> anyhow::Result<Box<dyn Agent>> {
+        Ok(match *self {
+            AgentSpec::Random => Box::new(RandomAgent),
+            AgentSpec::Heuristic => Box::new(HeuristicAgent),
+            AgentSpec::QTable {
+                n_games,
+                n_epoch,
+                ref options,
+                policy,
+            } => {
+                let mut agent = train(n_games, n_epoch, board, options);
+                agent.set_policy(policy);
+                Box::new(agent)
+            }
+            AgentSpec::SavedQTable { ref path, policy } => {
+                let mut agent = QTable::load(path)?;
+                agent.set_policy(policy);
+                Box::new(agent)
+            }
+            AgentSpec::Dqn {
+                n_games,
+                ref config,
+                policy,
+            } => {
+                let mut agent = train_dqn(n_games, board, config, &mut RandomAgent, rng);
+                agent.set_policy(policy);
+                Box::new(agent)
+            }
+            AgentSpec::Mcts {
+                budget,
+                heuristic_rollouts,
+            } => {
+                let rollout = if heuristic_rollouts {
+                    Rollout::Policy(Box::new(HeuristicAgent))
+                } else {
+                    Rollout::Random
+                };
+                Box::new(Mcts::new(MCTS_EXPLORATION, budget, rollout))
+            }
+            AgentSpec::Minimax { depth } => Box::new(MinimaxAgent::new(depth)),
+        })
+    }
 }
 
-type State = Vec<Vec<CellState>>;
+pub(crate) type State = Vec<Vec<CellState>>;
 
-#[derive(Hash, Eq, PartialEq, Clone)]
-struct StateAction {
+#[derive(Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub(crate) struct StateAction {
     state: State,
     action: Action,
 }
 
+/// Value of a state-action pair with its update history
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub q: f64,
+    /// Number of updates, used by count based exploration
+    pub visits: usize,
+    /// Training epoch of the last update
+    pub last_update: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedQTable")]
 pub struct QTable {
-    table: HashMap<StateAction, f64>,
+    #[serde(with = "map_as_entries")]
+    table: HashMap<StateAction, Entry>,
+    /// Training epoch stamped on the entries updated from now on
+    epoch: usize,
     alpha: f64,
     gamma: f64,
-    epsilon: f64,
+    /// Policy followed when the table plays as an agent
+    policy: Policy,
+    /// Largest change of a Q-value since the last call to `take_max_change`
+    #[serde(default)]
+    max_change: f64,
+    /// Experiment the table was trained by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    experiment: Option<Box<ExperimentConfig>>,
+}
+
+/// Value of an entry in a saved table, a bare Q-value in tables saved before the entries kept
+/// their update history
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedEntry {
+    Entry(Entry),
+    Q(f64),
+}
+
+/// Q-table as read from a file, in the current format or in the former one where the visits
+/// were kept in a map of their own
+#[derive(Deserialize)]
+struct SavedQTable {
+    table: Vec<(StateAction, SavedEntry)>,
+    #[serde(default, with = "map_as_entries")]
+    visits: HashMap<StateAction, usize>,
+    #[serde(default)]
+    epoch: usize,
+    alpha: f64,
+    gamma: f64,
+    policy: Policy,
+    #[serde(default)]
+    max_change: f64,
+    #[serde(default)]
+    experiment: Option<Box<ExperimentConfig>>,
+}
+
+impl From<SavedQTable> for QTable {
+    fn from(saved: SavedQTable) -> Self {
+        let table = saved
+            .table
+            .into_iter()
+            .map(|(key, entry)| {
+                let entry = match entry {
+                    SavedEntry::Entry(entry) => entry,
+                    SavedEntry::Q(q) => Entry {
+                        q,
+                        visits: saved.visits.get(&key).copied().unwrap_or(0),
+                        last_update: 0,
+                    },
+                };
+                (key, entry)
+            })
+            .collect();
+        Self {
+            table,
+            epoch: saved.epoch,
+            alpha: saved.alpha,
+            gamma: saved.gamma,
+            policy: saved.policy,
+            max_change: saved.max_change,
+            experiment: saved.experiment,
+        }
+    }
 }
 
 impl QTable {
-    fn new(alpha: f64, gamma: f64, epsilon: f64) -> Self {
+    fn new(alpha: f64, gamma: f64, policy: Policy) -> Self {
         Self {
             table: HashMap::new(),
+            epoch: 0,
             alpha,
             gamma,
-            epsilon,
+            policy,
+            max_change: 0.0,
+            experiment: None,
         }
     }
 
-    fn get_q(&self, state: &State, action: Action) -> f64 {
-        *self
-            .table
-            .get(&StateAction {
-                state: state.clone(),
+    /// Builds a greedy table holding the given values, e.g. computed by planning
+    pub(crate) fn from_values(
+        values: impl IntoIterator<Item = (State, Action, f64)>,
+        gamma: f64,
+    ) -> Self {
+        let mut table = Self::new(0.0, gamma, Policy::Greedy);
+        table.table = values
+            .into_iter()
+            .map(|(state, action, q)| {
+                let entry = Entry {
+                    q,
+                    visits: 0,
+                    last_update: 0,
+                };
+                (StateAction { state, action }, entry)
+            })
+            .collect();
+        table
+    }
+
+    /// Returns the largest change of a Q-value since the previous call
+    fn take_max_change(&mut self) -> f64 {
+        std::mem::take(&mut self.max_change)
+    }
+
+    /// Best action of every known state, ties go to the first action in row order so the result
+    /// doesn't depend on the iteration order of the table
+    pub(crate) fn greedy_actions(&self) -> HashMap<State, Action> {
+        let key = |action: Action| (action.layer, action.x_axis, action.y_axis, action.symbol);
+        let mut best: HashMap<State, (Action, f64)> = HashMap::new();
+        for (state_action, &Entry { q, .. }) in &self.table {
+            best.entry(state_action.state.clone())
+                .and_modify(|(action, best_q)| {
+                    if q > *best_q || (q == *best_q && key(state_action.action) < key(*action)) {
+                        *action = state_action.action;
+                        *best_q = q;
+                    }
+                })
+                .or_insert((state_action.action, q));
+        }
+        best.into_iter()
+            .map(|(state, (action, _))| (state, action))
+            .collect()
+    }
+
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+    }
+
+    pub fn experiment(&self) -> Option<&ExperimentConfig> {
+        self.experiment.as_deref()
+    }
+
+    /// Records the experiment the table was trained by
+    pub fn set_experiment(&mut self, experiment: &ExperimentConfig) {
+        self.experiment = Some(Box::new(experiment.clone()));
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        save_json(self, path)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        load_json(path)
+    }
+
+    /// Q-value and number of updates of every legal move
+    pub fn action_stats(&self, board: &Board) -> Vec<CellStats> {
+        let state = board.get_grid();
+        Action::possible_actions(board)
+            .into_iter()
+            .map(|action| CellStats {
                 action,
+                q: self.get_q(&state, action),
+                visits: self.get_visits(&state, action),
             })
-            .unwrap_or(&0.0)
+            .collect()
+    }
+
+    /// Every entry of the table, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = (&State, Action, &Entry)> {
+        self.table
+            .iter()
+            .map(|(state_action, entry)| (&state_action.state, state_action.action, entry))
+    }
+
+    fn get_entry(&self, state: &State, action: Action) -> Option<&Entry> {
+        self.table.get(&StateAction {
+            state: state.clone(),
+            action,
+        })
+    }
+
+    fn get_visits(&self, state: &State, action: Action) -> usize {
+        self.get_entry(state, action)
+            .map_or(0, |entry| entry.visits)
+    }
+
+    fn get_q(&self, state: &State, action: Action) -> f64 {
+        self.get_entry(state, action).map_or(0.0, |entry| entry.q)
     }
 
     fn update_table(
@@ -59,64 +428,213 @@ impl QTable {
         action: Action,
         state_after_action: &State,
         possible_actions: &[Action],
-        reward: i32,
+        reward: f64,
     ) {
+        self.update_with_step(
+            state,
+            action,
+            state_after_action,
+            possible_actions,
+            reward,
+            self.alpha,
+        );
+    }
+
+    /// Moves Q(state, action) towards its TD target by `step_size`, returns the TD error
+    fn update_with_step(
+        &mut self,
+        state: &State,
+        action: Action,
+        state_after_action: &State,
+        possible_actions: &[Action],
+        reward: f64,
+        step_size: f64,
+    ) -> f64 {
         let current_q = self.get_q(state, action);
         let max_q = possible_actions
             .iter()
             .map(|next_action| self.get_q(state_after_action, *next_action))
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap_or(0.0);
-        let new_q = current_q + self.alpha * (reward as f64 + self.gamma * max_q - current_q);
-        self.table.insert(
-            StateAction {
+        let td_error = reward + self.gamma * max_q - current_q;
+        let entry = self
+            .table
+            .entry(StateAction {
                 state: state.clone(),
                 action,
-            },
-            new_q,
-        );
+            })
+            .or_insert(Entry {
+                q: 0.0,
+                visits: 0,
+                last_update: 0,
+            });
+        entry.q = current_q + step_size * td_error;
+        entry.visits += 1;
+        entry.last_update = self.epoch;
+        self.max_change = self.max_change.max((step_size * td_error).abs());
+        td_error
     }
 
-    pub fn epsilon_greedy_search(&self, state: &State, possible_actions: &Vec<Action>) -> Action {
-        if rand::thread_rng().gen::<f64>() < self.epsilon {
-            *possible_actions.choose(&mut rand::thread_rng()).unwrap()
-        } else {
-            *possible_actions
-                .iter()
-                .max_by(|&a, &b| {
-                    self.get_q(state, *a)
-                        .partial_cmp(&self.get_q(state, *b))
-                        .unwrap()
-                })
-                .unwrap()
+    /// Replays a minibatch of stored transitions and refreshes their priorities
+    pub fn learn_from_replay<R: Rng + ?Sized>(
+        &mut self,
+        buffer: &mut ReplayBuffer<Transition>,
+        batch_size: usize,
+        rng: &mut R,
+    ) {
+        let Some(batch) = buffer.sample(batch_size, rng) else {
+            return;
+        };
+        for (&index, &weight) in batch.indices.iter().zip(batch.weights.iter()) {
+            let transition = buffer.get(index).clone();
+            let next_actions: &[Action] = if transition.done {
+                &[]
+            } else {
+                &transition.next_actions
+            };
+            let td_error = self.update_with_step(
+                &transition.state,
+                transition.action,
+                &transition.next_state,
+                next_actions,
+                transition.reward,
+                self.alpha * weight,
+            );
+            buffer.update_priority(index, td_error);
         }
     }
+
+    /// Greedy move of the table in any game
+    pub(crate) fn greedy_action<G: Game>(&self, game: &G, rng: &mut dyn RngCore) -> Action {
+        self.search(
+            &game.state(),
+            &game.possible_actions(),
+            &Policy::Greedy,
+            rng,
+        )
+    }
+
+    /// Chooses among the possible actions following the given policy
+    pub fn search<R: Rng + ?Sized>(
+        &self,
+        state: &State,
+        possible_actions: &[Action],
+        policy: &Policy,
+        rng: &mut R,
+    ) -> Action {
+        let q_values: Vec<f64> = possible_actions
+            .iter()
+            .map(|&action| self.get_q(state, action))
+            .collect();
+        let visits: Vec<usize> = possible_actions
+            .iter()
+            .map(|&action| self.get_visits(state, action))
+            .collect();
+        possible_actions[policy.select(&q_values, &visits, rng)]
+    }
+}
+
+impl Agent for QTable {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        self.search(
+            &board.get_grid(),
+            &Action::possible_actions(board),
+            &self.policy,
+            rng,
+        )
+    }
+
+    fn action_values(&self, board: &Board) -> Option<Vec<(Action, f64)>> {
+        let state = board.get_grid();
+        Some(
+            Action::possible_actions(board)
+                .into_iter()
+                .map(|action| (action, self.get_q(&state, action)))
+                .collect(),
+        )
+    }
 }
 
-struct Environment {
-    board: Board,
+/// Rules of a two player game the learners can train on through an `Environment`
+pub(crate) trait Game: Clone {
+    fn reset(&mut self);
+
+    fn play(&mut self, action: Action);
+
+    fn winner(&self) -> Option<CellState>;
+
+    /// Returns whether no move is left, a finished game without winner is a draw
+    fn is_full(&self) -> bool;
+
+    fn possible_actions(&self) -> Vec<Action>;
+
+    /// Key of the position in Q-tables
+    fn state(&self) -> State;
+
+    fn current_player(&self) -> CellState;
+}
+
+impl Game for Board {
+    fn reset(&mut self) {
+        Board::reset(self);
+    }
+
+    fn play(&mut self, action: Action) {
+        self.play_move(action.x_axis, action.y_axis);
+    }
+
+    fn winner(&self) -> Option<CellState> {
+        self.is_winner()
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_board_full()
+    }
+
+    fn possible_actions(&self) -> Vec<Action> {
+        Action::possible_actions(self)
+    }
+
+    fn state(&self) -> State {
+        self.get_grid()
+    }
+
+    fn current_player(&self) -> CellState {
+        self.get_current_player()
+    }
+}
+
+pub(crate) struct Environment<G = Board> {
+    pub(crate) board: G,
     reward: i32,
-    player: CellState,
+    pub(crate) player: CellState,
 }
 
+#[cfg(test)]
 impl Environment {
-    fn new(size: usize, win_condition: usize, player: CellState) -> Self {
+    pub(crate) fn new(config: BoardConfig, player: CellState) -> Self {
+        Self::from_game(Board::new(config), player)
+    }
+}
+
+impl<G: Game> Environment<G> {
+    pub(crate) fn from_game(game: G, player: CellState) -> Self {
         Self {
-            board: Board::new(size, win_condition),
+            board: game,
             reward: Reward::INTERMEDIATE,
             player,
         }
     }
 
-    fn reset(&mut self) {
+    pub(crate) fn reset(&mut self) {
         self.board.reset();
         self.reward = Reward::INTERMEDIATE;
     }
 
-    fn step(&mut self, action: Action) -> (State, i32) {
-        self.board.play_move(action.x_axis, action.y_axis);
+    pub(crate) fn step(&mut self, action: Action) -> (State, i32) {
+        self.board.play(action);
 
-        if let Some(winner) = self.board.is_winner() {
+        if let Some(winner) = self.board.winner() {
             self.reward = if winner == self.player {
                 Reward::WIN
             } else {
@@ -124,95 +642,839 @@ impl Environment {
             }
         };
 
-        if self.board.is_board_full() && self.reward == Reward::INTERMEDIATE {
+        if self.board.is_full() && self.reward == Reward::INTERMEDIATE {
             self.reward = Reward::DRAW;
         }
         (self.get_grid(), self.reward)
     }
 
-    fn get_possibe_moves(&self) -> Vec<Action> {
-        let grid = self.board.get_possible_actions();
-        grid.iter()
-            .map(|&(x_axis, y_axis)| Action { x_axis, y_axis })
-            .collect::<Vec<Action>>()
+    pub(crate) fn get_possibe_moves(&self) -> Vec<Action> {
+        self.board.possible_actions()
     }
 
-    fn get_grid(&self) -> State {
-        self.board.get_grid()
+    pub(crate) fn get_grid(&self) -> State {
+        self.board.state()
     }
 
-    fn get_player(&self) -> CellState {
-        self.board.get_current_player()
+    pub(crate) fn get_player(&self) -> CellState {
+        self.board.current_player()
     }
 }
 
-fn get_hyperparameters(epoch: usize, n_epoch: usize) -> (f64, f64, f64) {
-    let grid_alpha = [0.9, 0.6, 0.3, 0.2, 0.1];
-    let grid_epsilon = [0.5, 0.3, 0.1, 0.01];
-    let grid_gamma = [0.9, 0.95, 0.99];
-    let progress = epoch as f64 / n_epoch as f64;
+/// Values of the hyperparameters along the training, linearly interpolated between the points
+/// of every grid
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub alpha: Vec<f64>,
+    pub gamma: Vec<f64>,
+    pub epsilon: Vec<f64>,
+}
 
-    let interpolate = |grid: &[f64]| {
-        let idx = ((grid.len() - 1) as f64 * progress).floor() as usize;
-        let frac = (grid.len() - 1) as f64 * progress - idx as f64;
-        grid[idx] * (1.0 - frac) + grid[idx + 1] * frac
-    };
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            alpha: vec![0.9, 0.6, 0.3, 0.2, 0.1],
+            gamma: vec![0.9, 0.95, 0.99],
+            epsilon: vec![0.5, 0.3, 0.1, 0.01],
+        }
+    }
+}
+
+impl Schedule {
+    /// Checks that every grid has a point to interpolate from
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, grid) in [
+            ("alpha", &self.alpha),
+            ("gamma", &self.gamma),
+            ("epsilon", &self.epsilon),
+        ] {
+            ensure!(
+                !grid.is_empty(),
+                "The {} schedule needs at least one value",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns alpha, gamma and epsilon at the given epoch, the grids can't be empty
+    fn hyperparameters(&self, epoch: usize, n_epoch: usize) -> (f64, f64, f64) {
+        let progress = epoch as f64 / n_epoch as f64;
+
+        let interpolate = |grid: &[f64]| {
+            let position = (grid.len() - 1) as f64 * progress;
+            let idx = (position.floor() as usize).min(grid.len() - 1);
+            let frac = position - idx as f64;
+            match grid.get(idx + 1) {
+                Some(next) => grid[idx] * (1.0 - frac) + next * frac,
+                None => grid[idx],
+            }
+        };
+
+        let alpha = interpolate(&self.alpha);
+        let epsilon = interpolate(&self.epsilon);
+        let gamma = interpolate(&self.gamma);
+
+        (alpha, gamma, epsilon)
+    }
+}
+
+/// Rewards given to the learner at the end of a game
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rewards {
+    pub win: f64,
+    pub draw: f64,
+    pub loss: f64,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            win: Reward::WIN as f64,
+            draw: Reward::DRAW as f64,
+            loss: Reward::LOSS as f64,
+        }
+    }
+}
+
+impl Rewards {
+    /// Value of an outcome returned by the environment
+    fn value(&self, reward: i32) -> f64 {
+        match reward {
+            Reward::WIN => self.win,
+            Reward::DRAW => self.draw,
+            Reward::LOSS => self.loss,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Optional features of the Q-learning training loop
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrainingOptions {
+    /// When set, transitions are stored and replayed in minibatches after every game instead of
+    /// being applied immediately
+    pub replay: Option<ReplayConfig>,
+    pub exploration: Exploration,
+    /// When set, the learner plays against this mix of opponents instead of itself
+    pub league: Option<League>,
+    /// Seed of the random generator, drawn from the system when missing
+    pub seed: Option<u64>,
+    pub checkpoint: Option<CheckpointConfig>,
+    pub early_stopping: Option<EarlyStopping>,
+    #[serde(default)]
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub rewards: Rewards,
+    /// Position the games start from instead of the empty board, see `Board::from_position`
+    #[serde(default)]
+    pub start: Option<String>,
+    /// Hides the report of every epoch
+    #[serde(default)]
+    pub quiet: bool,
+}
+
+/// Applies the transition right away, or stores it until the next replay
+fn record_transition(
+    agent: &mut QTable,
+    replay: &mut Option<ReplayBuffer<Transition>>,
+    transition: Transition,
+) {
+    match replay {
+        Some(buffer) => buffer.push(transition),
+        None => agent.update_table(
+            &transition.state,
+            transition.action,
+            &transition.next_state,
+            &transition.next_actions,
+            transition.reward,
+        ),
+    }
+}
+
+/// Plays a game where the table chooses the moves of both sides, returns the final reward of
+/// `player`
+fn play_self_play_game<G: Game>(
+    agent: &mut QTable,
+    env: &mut Environment<G>,
+    player: CellState,
+    replay: &mut Option<ReplayBuffer<Transition>>,
+    rewards: &Rewards,
+    rng: &mut ChaCha8Rng,
+) -> i32 {
+    let mut state = env.get_grid();
+    let mut possible_actions = env.get_possibe_moves();
+
+    loop {
+        let action = agent.search(&state, &possible_actions, &agent.policy, rng);
+        let (next_state, reward) = env.step(action);
+        possible_actions = env.get_possibe_moves();
+        if env.get_player() == player {
+            let transition = Transition {
+                state: state.clone(),
+                action,
+                reward: rewards.value(reward),
+                next_state: next_state.clone(),
+                next_actions: possible_actions.clone(),
+                done: reward != Reward::INTERMEDIATE,
+            };
+            record_transition(agent, replay, transition);
+            state = next_state;
+        }
+        if reward != Reward::INTERMEDIATE {
+            return reward;
+        }
+    }
+}
+
+/// Plays a game of the table as `player` against the opponent, transitions go from one position
+/// of the learner to its next one, after the opponent replied. Returns the final reward.
+fn play_league_game(
+    agent: &mut QTable,
+    env: &mut Environment,
+    opponent: &mut dyn Agent,
+    player: CellState,
+    replay: &mut Option<ReplayBuffer<Transition>>,
+    rewards: &Rewards,
+    rng: &mut ChaCha8Rng,
+) -> i32 {
+    let mut reward = Reward::INTERMEDIATE;
+    if env.get_player() != player {
+        (_, reward) = env.step(opponent.select_action(&env.board, rng));
+    }
+
+    while reward == Reward::INTERMEDIATE {
+        let state = env.get_grid();
+        let action = agent.search(&state, &env.get_possibe_moves(), &agent.policy, rng);
+        (_, reward) = env.step(action);
+        if reward == Reward::INTERMEDIATE {
+            (_, reward) = env.step(opponent.select_action(&env.board, rng));
+        }
+        let transition = Transition {
+            state,
+            action,
+            reward: rewards.value(reward),
+            next_state: env.get_grid(),
+            next_actions: env.get_possibe_moves(),
+            done: reward != Reward::INTERMEDIATE,
+        };
+        record_transition(agent, replay, transition);
+    }
+    reward
+}
+
+/// Counts of game outcomes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct OutcomeCounts {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+impl OutcomeCounts {
+    fn record(&mut self, reward: i32) {
+        match reward {
+            Reward::WIN => self.wins += 1,
+            Reward::DRAW => self.draws += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    fn rates(&self) -> (f64, f64, f64) {
+        let n_games = (self.wins + self.draws + self.losses).max(1) as f64;
+        (
+            self.wins as f64 / n_games,
+            self.draws as f64 / n_games,
+            self.losses as f64 / n_games,
+        )
+    }
+}
+
+/// Summary of a finished epoch
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpochMetrics {
+    pub epoch: usize,
+    pub win_rate: f64,
+    pub draw_rate: f64,
+    pub loss_rate: f64,
+    pub alpha: f64,
+    pub gamma: f64,
+    pub epsilon: f64,
+    pub max_q_change: f64,
+    pub policy_change: f64,
+    pub score: Option<f64>,
+}
+
+/// Everything needed to continue a training run exactly where it stopped, this is what
+/// checkpoints contain
+#[derive(Serialize, Deserialize)]
+pub struct TrainingState {
+    n_games: usize,
+    n_epoch: usize,
+    board: BoardConfig,
+    options: TrainingOptions,
+    /// Next epoch to play
+    epoch: usize,
+    /// Next game to play within the epoch
+    game: usize,
+    agent: QTable,
+    rng: ChaCha8Rng,
+    metrics: Vec<EpochMetrics>,
+    outcomes: OutcomeCounts,
+    opponent_outcomes: Vec<OutcomeCounts>,
+    replay: Option<ReplayBuffer<Transition>>,
+    league: Option<LeaguePool>,
+    convergence: ConvergenceTracker,
+    /// Set when a convergence criterion ended the training early
+    early_stop: Option<StopReason>,
+    /// Experiment stamped into the trained table and the metrics
+    #[serde(default)]
+    experiment: Option<ExperimentConfig>,
+}
+
+/// Content of the metrics file of a training
+#[derive(Serialize)]
+struct MetricsReport<'a> {
+    experiment: Option<&'a ExperimentConfig>,
+    stop_reason: StopReason,
+    metrics: &'a [EpochMetrics],
+}
+
+impl TrainingState {
+    pub fn new(
+        n_games: usize,
+        n_epoch: usize,
+        board: BoardConfig,
+        options: &TrainingOptions,
+    ) -> Self {
+        let (alpha, gamma, epsilon) = options.schedule.hyperparameters(0, n_epoch);
+        let league = options.league.as_ref().map(LeaguePool::new);
+        Self {
+            n_games,
+            n_epoch,
+            board,
+            options: options.clone(),
+            epoch: 0,
+            game: 0,
+            agent: QTable::new(alpha, gamma, options.exploration.policy(0.0, epsilon)),
+            rng: match options.seed {
+                Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+                None => ChaCha8Rng::from_entropy(),
+            },
+            metrics: Vec::new(),
+            outcomes: OutcomeCounts::default(),
+            opponent_outcomes: vec![
+                OutcomeCounts::default();
+                league.as_ref().map_or(0, |pool| pool.n_opponents())
+            ],
+            replay: options
+                .replay
+                .map(|config| ReplayBuffer::new(config.capacity, config.sampling)),
+            league,
+            convergence: ConvergenceTracker::default(),
+            early_stop: None,
+            experiment: None,
+        }
+    }
+
+    /// Builds the training described by an experiment
+    pub fn from_experiment(experiment: &ExperimentConfig) -> Self {
+        let state = Self::new(
+            experiment.learner.n_games,
+            experiment.learner.n_epoch,
+            experiment.environment,
+            &TrainingOptions {
+                checkpoint: experiment.outputs.checkpoint.clone(),
+                ..experiment.training_options()
+            },
+        );
+        Self {
+            experiment: Some(experiment.clone()),
+            ..state
+        }
+    }
+
+    /// Loads a checkpoint to resume its training
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let state: Self = load_json(path)?;
+        println!(
+            "Resuming training at epoch {}, game {}",
+            state.epoch, state.game
+        );
+        Ok(state)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        save_json(self, path)
+    }
+
+    /// Returns whether the training is over, false after an interruption
+    pub fn is_finished(&self) -> bool {
+        self.epoch >= self.n_epoch || self.early_stop.is_some()
+    }
+
+    pub fn stop_reason(&self) -> StopReason {
+        match self.early_stop {
+            Some(reason) => reason,
+            None if self.epoch >= self.n_epoch => StopReason::Completed,
+            None => StopReason::Interrupted,
+        }
+    }
+
+    /// Returns the trained table, stamped with the experiment if any
+    pub fn into_agent(self) -> QTable {
+        QTable {
+            experiment: self.experiment.map(Box::new),
+            ..self.agent
+        }
+    }
+
+    /// Writes the metrics of every epoch along with the experiment they come from
+    pub fn save_metrics(&self, path: &Path) -> anyhow::Result<()> {
+        let report = MetricsReport {
+            experiment: self.experiment.as_ref(),
+            stop_reason: self.stop_reason(),
+            metrics: &self.metrics,
+        };
+        save_json(&report, path)
+    }
+
+    /// File the state is saved to, the configured checkpoint or else a file next to the table
+    /// of the experiment, so an interrupted training can still be resumed
+    pub fn checkpoint_path(&self) -> Option<PathBuf> {
+        match (&self.options.checkpoint, &self.experiment) {
+            (Some(config), _) => Some(config.path.clone()),
+            (None, Some(experiment)) => {
+                Some(experiment.outputs.table.with_extension("checkpoint.json"))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Saves the state if it has a checkpoint path
+    fn checkpoint(&self) {
+        if let Some(path) = self.checkpoint_path() {
+            match self.save(&path) {
+                Ok(()) => println!(
+                    "Saved checkpoint at epoch {}, game {} to {}",
+                    self.epoch,
+                    self.game,
+                    path.display()
+                ),
+                Err(err) => eprintln!("Cannot save checkpoint, error {:#}", err),
+            }
+        }
+    }
+
+    /// Trains until the last epoch, or until Ctrl-C is pressed in which case a checkpoint is
+    /// saved before returning
+    pub fn run(self) -> Self {
+        self.run_until(|_| interrupted())
+    }
+
+    /// Trains until the last epoch, or until `stop` holds before a game in which case a
+    /// checkpoint is saved before returning
+    fn run_until(mut self, mut stop: impl FnMut(&Self) -> bool) -> Self {
+        let board = self.start_board();
+        let mut env = Environment::from_game(board, CellState::Empty);
+        while !self.is_finished() {
+            if !self.play_epoch(&mut env, &mut stop) {
+                self.checkpoint();
+                break;
+            }
+            if let Some(config) = &self.options.checkpoint {
+                if self.epoch.is_multiple_of(config.every.max(1)) || self.is_finished() {
+                    self.checkpoint();
+                }
+            }
+        }
+        if !self.options.quiet {
+            println!(
+                "Training stopped after {} epochs: {}",
+                self.epoch,
+                self.stop_reason()
+            );
+        }
+        self
+    }
+
+    /// Plays the rest of the current epoch, returns false if stopped before its end
+    fn play_epoch(&mut self, env: &mut Environment, stop: &mut impl FnMut(&Self) -> bool) -> bool {
+        if self.game == 0 {
+            self.outcomes = OutcomeCounts::default();
+            self.opponent_outcomes.fill(OutcomeCounts::default());
+            if let Some(pool) = &mut self.league {
+                pool.maybe_snapshot(&self.agent, self.epoch);
+            }
+        }
+        while self.game < self.n_games {
+            if stop(self) {
+                return false;
+            }
+            self.play_game(env);
+            self.game += 1;
+        }
+        self.end_epoch();
+        self.epoch += 1;
+        self.game = 0;
+        true
+    }
+
+    fn play_game(&mut self, env: &mut Environment) {
+        self.agent.epoch = self.epoch;
+        env.reset();
+        let player = if self.rng.gen::<f64>() > 0.5 {
+            CellState::X
+        } else {
+            CellState::O
+        };
+        env.player = player;
+
+        let reward = match &mut self.league {
+            Some(pool) => {
+                let index = pool.sample(&mut self.rng);
+                let opponent = pool.opponent(index, &mut self.rng);
+                let reward = play_league_game(
+                    &mut self.agent,
+                    env,
+                    opponent,
+                    player,
+                    &mut self.replay,
+                    &self.options.rewards,
+                    &mut self.rng,
+                );
+                self.opponent_outcomes[index].record(reward);
+                reward
+            }
+            None => play_self_play_game(
+                &mut self.agent,
+                env,
+                player,
+                &mut self.replay,
+                &self.options.rewards,
+                &mut self.rng,
+            ),
+        };
+        self.outcomes.record(reward);
 
-    let alpha = interpolate(&grid_alpha);
-    let epsilon = interpolate(&grid_epsilon);
-    let gamma = interpolate(&grid_gamma);
+        if let (Some(buffer), Some(config)) = (&mut self.replay, &self.options.replay) {
+            self.agent
+                .learn_from_replay(buffer, config.batch_size, &mut self.rng);
+        }
+    }
 
-    (alpha, gamma, epsilon)
+    /// Moves the hyperparameters along their schedule and reports the epoch
+    fn end_epoch(&mut self) {
+        let (alpha, gamma, epsilon) = self
+            .options
+            .schedule
+            .hyperparameters(self.epoch, self.n_epoch);
+        self.agent.alpha = alpha;
+        self.agent.gamma = gamma;
+        self.agent.policy = self
+            .options
+            .exploration
+            .policy(self.epoch as f64 / self.n_epoch as f64, epsilon);
+        let (win_rate, draw_rate, loss_rate) = self.outcomes.rates();
+        let change = self.epoch_change();
+        if !self.options.quiet {
+            println!(
+                "Epoch: {}, win rate: {}, draw rate {}, loss rate {}, hyper params {:?}, policy {:?}",
+                self.epoch,
+                win_rate,
+                draw_rate,
+                loss_rate,
+                (alpha, gamma, epsilon),
+                self.agent.policy
+            );
+            if let Some(pool) = &self.league {
+                for (index, outcomes) in self.opponent_outcomes.iter().enumerate() {
+                    println!(
+                        "    vs {:?}: (win, draw, loss) rates {:?}",
+                        pool.kind(index),
+                        outcomes.rates()
+                    );
+                }
+            }
+            println!(
+                "    max Q change {}, policy change {}, score {:?}",
+                change.max_q_change, change.policy_change, change.score
+            );
+        }
+        if let Some(config) = &self.options.early_stopping {
+            self.early_stop = self.convergence.update(config, &change);
+        }
+        self.metrics.push(EpochMetrics {
+            epoch: self.epoch,
+            win_rate,
+            draw_rate,
+            loss_rate,
+            alpha,
+            gamma,
+            epsilon,
+            max_q_change: change.max_q_change,
+            policy_change: change.policy_change,
+            score: change.score,
+        });
+    }
+
+    fn start_board(&self) -> Board {
+        Board::from_start(self.options.start.as_deref(), self.board)
+            .expect("The start position was checked with the experiment")
+    }
+
+    /// Measures how much the table and its greedy policy moved during the epoch
+    fn epoch_change(&mut self) -> EpochChange {
+        let max_q_change = self.agent.take_max_change();
+        let policy_change = self.convergence.policy_change(self.agent.greedy_actions());
+        let plateau = self
+            .options
+            .early_stopping
+            .as_ref()
+            .and_then(|config| config.plateau.as_ref());
+        let start = self.start_board();
+        let score = plateau.map(|plateau| {
+            let mut greedy = self.agent.clone();
+            greedy.set_policy(Policy::Greedy);
+            let mut opponent = plateau
+                .opponent
+                .build()
+                .expect("Plateaus against snapshots are rejected when read");
+            evaluate(
+                &mut greedy,
+                opponent.as_mut(),
+                plateau.n_games,
+                &start,
+                &mut self.rng,
+            )
+            .score()
+        });
+        EpochChange {
+            max_q_change,
+            policy_change,
+            score,
+        }
+    }
+}
+
+pub fn train(
+    n_games: usize,
+    n_epoch: usize,
+    board: BoardConfig,
+    options: &TrainingOptions,
+) -> QTable {
+    TrainingState::new(n_games, n_epoch, board, options)
+        .run()
+        .into_agent()
 }
 
-pub fn train(n_games: usize, n_epoch: usize, size: usize, win_condition: usize) -> QTable {
-    let (mut alpha, mut gamma, mut epsilon) = get_hyperparameters(0, n_epoch);
-    let mut agent = QTable::new(alpha, gamma, epsilon);
-    let mut env = Environment::new(size, win_condition, CellState::Empty);
+/// Trains a table by self-play on any game, following the schedule, exploration, replay and
+/// rewards of the options. Leagues, checkpoints and early stopping need a board, see
+/// `TrainingState`, so options asking for them are rejected.
+pub(crate) fn train_self_play<G: Game>(
+    game: G,
+    n_games: usize,
+    n_epoch: usize,
+    options: &TrainingOptions,
+) -> anyhow::Result<QTable> {
+    for (setting, is_set) in [
+        ("league of opponents", options.league.is_some()),
+        ("checkpoint", options.checkpoint.is_some()),
+        ("early stopping", options.early_stopping.is_some()),
+        ("start position", options.start.is_some()),
+    ] {
+        ensure!(
+            !is_set,
+            "Self-play games don't support a {}, only boards do",
+            setting
+        );
+    }
+    let (alpha, gamma, epsilon) = options.schedule.hyperparameters(0, n_epoch);
+    let mut agent = QTable::new(alpha, gamma, options.exploration.policy(0.0, epsilon));
+    let mut rng = match options.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+    let mut replay = options
+        .replay
+        .map(|config| ReplayBuffer::new(config.capacity, config.sampling));
+    let mut env = Environment::from_game(game, CellState::Empty);
     for epoch in 0..n_epoch {
-        let mut n_wins = 0;
-        let mut n_draws = 0;
-        for _game in 0..n_games {
+        agent.epoch = epoch;
+        let mut outcomes = OutcomeCounts::default();
+        for _ in 0..n_games {
             env.reset();
-            let player = if rand::thread_rng().gen::<f64>() > 0.5 {
+            let player = if rng.gen::<f64>() > 0.5 {
                 CellState::X
             } else {
                 CellState::O
             };
             env.player = player;
-            let mut state = env.get_grid();
-            let mut possible_actions = env.get_possibe_moves();
-
-            loop {
-                let action = agent.epsilon_greedy_search(&state, &possible_actions);
-                let (next_state, reward) = env.step(action);
-                possible_actions = env.get_possibe_moves();
-                if env.get_player() == player {
-                    agent.update_table(&state, action, &next_state, &possible_actions, reward);
-                    state = next_state;
-                }
-                if reward != Reward::INTERMEDIATE {
-                    if reward == Reward::WIN {
-                        n_wins += 1
-                    } else if reward == Reward::DRAW {
-                        n_draws += 1
-                    };
-                    break;
-                }
+            let reward = play_self_play_game(
+                &mut agent,
+                &mut env,
+                player,
+                &mut replay,
+                &options.rewards,
+                &mut rng,
+            );
+            outcomes.record(reward);
+            if let (Some(buffer), Some(config)) = (&mut replay, &options.replay) {
+                agent.learn_from_replay(buffer, config.batch_size, &mut rng);
             }
         }
-        (alpha, gamma, epsilon) = get_hyperparameters(epoch, n_epoch);
+        let (alpha, gamma, epsilon) = options.schedule.hyperparameters(epoch, n_epoch);
         agent.alpha = alpha;
         agent.gamma = gamma;
-        agent.epsilon = epsilon;
-        println!(
-            "Epoch: {}, win rate: {}, draw rate {}, loss rate {}, hyper params {:?}",
-            epoch,
-            n_wins as f64 / n_games as f64,
-            n_draws as f64 / n_games as f64,
-            (n_games - n_wins - n_draws) as f64 / n_games as f64,
-            (alpha, gamma, epsilon)
+        agent.policy = options
+            .exploration
+            .policy(epoch as f64 / n_epoch as f64, epsilon);
+        if !options.quiet {
+            let (win_rate, draw_rate, loss_rate) = outcomes.rates();
+            println!(
+                "Epoch: {}, win rate: {}, draw rate {}, loss rate {}, hyper params {:?}",
+                epoch,
+                win_rate,
+                draw_rate,
+                loss_rate,
+                (alpha, gamma, epsilon)
+            );
+        }
+    }
+    Ok(agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{league::League, logic::Variant};
+
+    #[test]
+    fn test_resumed_training_matches_uninterrupted_run() {
+        let path =
+            std::env::temp_dir().join(format!("ttt_rl_checkpoint_{}.json", std::process::id()));
+        let options = TrainingOptions {
+            // A snapshot is due at the epoch the training stops in
+            league: Some(League {
+                snapshot_every: 1,
+                max_snapshots: 2,
+                ..("random=1,snapshot=1".parse::<League>().unwrap())
+            }),
+            seed: Some(7),
+            checkpoint: Some(CheckpointConfig {
+                path: path.clone(),
+                every: 10,
+            }),
+            ..TrainingOptions::default()
+        };
+        let expected = TrainingState::new(50, 4, BoardConfig::default(), &options).run();
+
+        // Stops in the middle of the second epoch, which saves the checkpoint
+        let stopped = TrainingState::new(50, 4, BoardConfig::default(), &options)
+            .run_until(|state| state.epoch == 1 && state.game == 20);
+        assert!(!stopped.is_finished());
+        let resumed = TrainingState::load(&path).unwrap().run();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(resumed.is_finished());
+        assert_eq!(resumed.metrics, expected.metrics);
+        assert_eq!(resumed.outcomes, expected.outcomes);
+        assert_eq!(resumed.opponent_outcomes, expected.opponent_outcomes);
+        assert!(resumed.agent.table == expected.agent.table);
+    }
+
+    #[test]
+    fn test_table_without_entry_history_loads() {
+        // Visits were kept apart from the Q-values before the entries had their history
+        let json = r#"{
+            "table": [[{"state": [["X", "Empty"], ["Empty", "O"]], "action": {"x_axis": 0, "y_axis": 1}}, 0.5]],
+            "visits": [[{"state": [["X", "Empty"], ["Empty", "O"]], "action": {"x_axis": 0, "y_axis": 1}}, 3]],
+            "alpha": 0.1,
+            "gamma": 0.9,
+            "policy": "Greedy",
+            "max_change": 0.0
+        }"#;
+        let table: QTable = serde_json::from_str(json).unwrap();
+        let state = vec![
+            vec![CellState::X, CellState::Empty],
+            vec![CellState::Empty, CellState::O],
+        ];
+        let action = Action {
+            x_axis: 0,
+            y_axis: 1,
+            layer: 0,
+            symbol: None,
+        };
+        assert_eq!(table.get_q(&state, action), 0.5);
+        assert_eq!(table.get_visits(&state, action), 3);
+
+        let json = serde_json::to_string(&table).unwrap();
+        let reloaded: QTable = serde_json::from_str(&json).unwrap();
+        assert!(reloaded.table == table.table);
+    }
+
+    #[test]
+    fn test_interrupted_training_always_has_a_checkpoint() {
+        let experiment = ExperimentConfig::default();
+        let state = TrainingState::from_experiment(&experiment);
+        assert_eq!(
+            state.checkpoint_path(),
+            Some(PathBuf::from("q_table.checkpoint.json"))
         );
+        let state = TrainingState::new(1, 1, BoardConfig::default(), &TrainingOptions::default());
+        assert_eq!(state.checkpoint_path(), None);
+    }
+
+    #[test]
+    fn test_training_stops_early() {
+        let options = TrainingOptions {
+            seed: Some(3),
+            early_stopping: Some(EarlyStopping {
+                max_q_change: Some(10.0),
+                patience: 2,
+                ..EarlyStopping::default()
+            }),
+            ..TrainingOptions::default()
+        };
+        let state = TrainingState::new(20, 10, BoardConfig::default(), &options).run();
+        assert!(state.is_finished());
+        assert_eq!(state.metrics.len(), 2);
+        assert!(matches!(
+            state.stop_reason(),
+            StopReason::QValuesConverged { .. }
+        ));
+    }
+
+    #[test]
+    fn test_misere_line_is_a_loss() {
+        let config = BoardConfig {
+            variant: Variant::Misere,
+            ..BoardConfig::default()
+        };
+        let mut env = Environment::new(config, CellState::X);
+        for (x_axis, y_axis) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let action = Action {
+                x_axis,
+                y_axis,
+                layer: 0,
+                symbol: None,
+            };
+            assert_eq!(env.step(action).1, Reward::INTERMEDIATE);
+        }
+        let action = Action {
+            x_axis: 2,
+            y_axis: 0,
+            layer: 0,
+            symbol: None,
+        };
+        assert_eq!(env.step(action).1, Reward::LOSS);
+
+        let board = Board::from_position("XX./OO./...", config).unwrap();
+        for _ in 0..20 {
+            assert_ne!(
+                HeuristicAgent.select_action(&board, &mut rand::thread_rng()),
+                action
+            );
+        }
     }
-    agent
 }
@@ -0,0 +1,75 @@
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C request a graceful stop of the training instead of killing the process
+pub fn install_interrupt_handler() -> anyhow::Result<()> {
+    ctrlc::set_handler(|| {
+        println!("Interrupted, stopping after the current game");
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    })
+    .context("Cannot install the Ctrl-C handler")
+}
+
+/// Returns whether a stop was requested with Ctrl-C
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Where and how often the training state is saved
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    /// Number of epochs between two checkpoints
+    pub every: usize,
+}
+
+/// Writes the value as JSON, through a temporary file so an interruption never leaves a
+/// truncated file behind
+pub fn save_json<T: Serialize>(value: &T, path: &Path) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let file =
+        File::create(&tmp_path).with_context(|| format!("Cannot create {}", tmp_path.display()))?;
+    serde_json::to_writer(BufWriter::new(file), value)
+        .with_context(|| format!("Cannot write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Cannot parse {}", path.display()))
+}
+
+/// Serializes maps as lists of entries, JSON only supports string keys
+pub mod map_as_entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::{collections::HashMap, hash::Hash};
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
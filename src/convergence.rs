@@ -0,0 +1,220 @@
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    agent::{Action, State},
+    checkpoint::map_as_entries,
+    league::OpponentKind,
+};
+
+/// Criteria ending a training before its last epoch, the first one holding for `patience`
+/// consecutive epochs stops it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EarlyStopping {
+    /// Threshold on the largest change of a single Q-value during an epoch
+    pub max_q_change: Option<f64>,
+    /// Threshold on the fraction of known states whose greedy action changed during an epoch
+    pub policy_change: Option<f64>,
+    pub plateau: Option<Plateau>,
+    pub patience: usize,
+}
+
+/// Evaluation of the greedy policy against a fixed opponent after every epoch, the score
+/// plateaus when it stops improving on its best value by more than `min_delta`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "PlateauFields")]
+pub struct Plateau {
+    pub opponent: OpponentKind,
+    pub n_games: usize,
+    pub min_delta: f64,
+}
+
+/// Plateau as written in configs and checkpoints, checked before the training evaluates against
+/// its opponent
+#[derive(Deserialize)]
+struct PlateauFields {
+    opponent: OpponentKind,
+    n_games: usize,
+    min_delta: f64,
+}
+
+impl TryFrom<PlateauFields> for Plateau {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: PlateauFields) -> anyhow::Result<Self> {
+        let plateau = Self {
+            opponent: fields.opponent,
+            n_games: fields.n_games,
+            min_delta: fields.min_delta,
+        };
+        plateau.validate()?;
+        Ok(plateau)
+    }
+}
+
+impl Plateau {
+    /// Checks that the opponent can be built on its own, snapshots only exist in a league
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.opponent != OpponentKind::Snapshot,
+            "Snapshots can't be evaluation opponents"
+        );
+        Ok(())
+    }
+}
+
+/// Why a training ended
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StopReason {
+    Completed,
+    Interrupted,
+    QValuesConverged { max_change: f64 },
+    PolicyStable { changed_fraction: f64 },
+    ScorePlateau { best_score: f64 },
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Completed => write!(f, "all epochs were played"),
+            StopReason::Interrupted => write!(f, "interrupted"),
+            StopReason::QValuesConverged { max_change } => {
+                write!(f, "Q-values converged, largest change {}", max_change)
+            }
+            StopReason::PolicyStable { changed_fraction } => write!(
+                f,
+                "policy is stable, greedy action changed in {} of the states",
+                changed_fraction
+            ),
+            StopReason::ScorePlateau { best_score } => {
+                write!(f, "evaluation score plateaued, best score {}", best_score)
+            }
+        }
+    }
+}
+
+/// What changed during an epoch
+#[derive(Clone, Copy, Debug)]
+pub struct EpochChange {
+    pub max_q_change: f64,
+    pub policy_change: f64,
+    /// Evaluation score, only computed when a plateau criterion is set
+    pub score: Option<f64>,
+}
+
+/// Number of consecutive epochs each criterion held, kept in checkpoints
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConvergenceTracker {
+    q_streak: usize,
+    policy_streak: usize,
+    plateau_streak: usize,
+    best_score: Option<f64>,
+    #[serde(with = "map_as_entries")]
+    greedy_actions: HashMap<State, Action>,
+}
+
+impl ConvergenceTracker {
+    /// Stores the greedy actions of the epoch and returns the fraction of states whose action
+    /// changed since the previous epoch, new states count as changed
+    pub fn policy_change(&mut self, greedy_actions: HashMap<State, Action>) -> f64 {
+        let n_changed = greedy_actions
+            .iter()
+            .filter(|(state, action)| self.greedy_actions.get(*state) != Some(*action))
+            .count();
+        let fraction = n_changed as f64 / greedy_actions.len().max(1) as f64;
+        self.greedy_actions = greedy_actions;
+        fraction
+    }
+
+    /// Returns why the training should stop, if one of the criteria held long enough
+    pub fn update(&mut self, config: &EarlyStopping, change: &EpochChange) -> Option<StopReason> {
+        let patience = config.patience.max(1);
+        let streak = |streak: &mut usize, holds: bool| {
+            *streak = if holds { *streak + 1 } else { 0 };
+            *streak >= patience
+        };
+
+        if let Some(threshold) = config.max_q_change {
+            if streak(&mut self.q_streak, change.max_q_change < threshold) {
+                return Some(StopReason::QValuesConverged {
+                    max_change: change.max_q_change,
+                });
+            }
+        }
+        if let Some(threshold) = config.policy_change {
+            if streak(&mut self.policy_streak, change.policy_change < threshold) {
+                return Some(StopReason::PolicyStable {
+                    changed_fraction: change.policy_change,
+                });
+            }
+        }
+        if let (Some(plateau), Some(score)) = (&config.plateau, change.score) {
+            let improved = self
+                .best_score
+                .is_none_or(|best| score > best + plateau.min_delta);
+            if improved {
+                self.best_score = Some(score);
+            }
+            if streak(&mut self.plateau_streak, !improved) {
+                return Some(StopReason::ScorePlateau {
+                    best_score: self.best_score.unwrap_or(score),
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(max_q_change: f64, score: f64) -> EpochChange {
+        EpochChange {
+            max_q_change,
+            policy_change: 1.0,
+            score: Some(score),
+        }
+    }
+
+    #[test]
+    fn test_criteria_need_patience() {
+        let config = EarlyStopping {
+            max_q_change: Some(0.01),
+            policy_change: Some(0.0),
+            plateau: None,
+            patience: 2,
+        };
+        let mut tracker = ConvergenceTracker::default();
+        assert_eq!(tracker.update(&config, &change(0.001, 0.0)), None);
+        // A large change resets the streak
+        assert_eq!(tracker.update(&config, &change(0.5, 0.0)), None);
+        assert_eq!(tracker.update(&config, &change(0.001, 0.0)), None);
+        assert_eq!(
+            tracker.update(&config, &change(0.002, 0.0)),
+            Some(StopReason::QValuesConverged { max_change: 0.002 })
+        );
+    }
+
+    #[test]
+    fn test_score_plateau() {
+        let config = EarlyStopping {
+            plateau: Some(Plateau {
+                opponent: OpponentKind::Random,
+                n_games: 10,
+                min_delta: 0.05,
+            }),
+            patience: 2,
+            ..EarlyStopping::default()
+        };
+        let mut tracker = ConvergenceTracker::default();
+        for score in [0.5, 0.7, 0.72] {
+            assert_eq!(tracker.update(&config, &change(1.0, score)), None);
+        }
+        assert_eq!(
+            tracker.update(&config, &change(1.0, 0.6)),
+            Some(StopReason::ScorePlateau { best_score: 0.7 })
+        );
+    }
+}
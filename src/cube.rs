@@ -0,0 +1,254 @@
+use anyhow::ensure;
+
+use crate::{
+    agent::{Action, Game, State},
+    logic::{find_line, BoardConfig, CellState, Variant},
+};
+
+/// Cells of a line of the cube, as x_axis, y_axis and layer
+pub type Coords3 = Vec<(usize, usize, usize)>;
+
+/// Generates `length` coordinates from the start along the direction
+fn generate_coords3(
+    start: (usize, usize, usize),
+    length: usize,
+    direction: (isize, isize, isize),
+) -> Coords3 {
+    (0..length as isize)
+        .map(|step| {
+            (
+                (start.0 as isize + step * direction.0) as usize,
+                (start.1 as isize + step * direction.1) as usize,
+                (start.2 as isize + step * direction.2) as usize,
+            )
+        })
+        .collect()
+}
+
+/// Lists once every maximal line of the cube holding at least `minimal_length` cells, along the
+/// 13 directions of space: 3 axes, 6 plane diagonals and 4 space diagonals
+pub fn calculate_lines_coords(size: usize, minimal_length: usize) -> Vec<Coords3> {
+    // A direction and its opposite give the same lines, only the one whose first non null
+    // component is positive is kept
+    let mut directions = Vec::with_capacity(13);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                if [dx, dy, dz].into_iter().find(|&d| d != 0) == Some(1) {
+                    directions.push((dx, dy, dz));
+                }
+            }
+        }
+    }
+    let inside = |coordinate: isize| (0..size as isize).contains(&coordinate);
+    let mut lines = Vec::new();
+    for direction in directions {
+        for x_axis in 0..size {
+            for y_axis in 0..size {
+                for layer in 0..size {
+                    let (x, y, z) = (x_axis as isize, y_axis as isize, layer as isize);
+                    // Lines start on the cell whose predecessor is out of the cube
+                    if inside(x - direction.0) && inside(y - direction.1) && inside(z - direction.2)
+                    {
+                        continue;
+                    }
+                    let length = (0..)
+                        .take_while(|&step| {
+                            inside(x + step * direction.0)
+                                && inside(y + step * direction.1)
+                                && inside(z + step * direction.2)
+                        })
+                        .count();
+                    if length >= minimal_length {
+                        lines.push(generate_coords3((x_axis, y_axis, layer), length, direction));
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// 3D tic-tac-toe on a cube of `size` layers of `size`x`size` cells, aligning `win_condition`
+/// symbols in any of the 13 directions wins, e.g. Qubic is 4x4x4 with four in a row
+#[derive(Clone, Debug)]
+pub struct Cube {
+    /// Cells indexed by layer, then x then y like a `Board`
+    grid: Vec<State>,
+    size: usize,
+    win_condition: usize,
+    turn: CellState,
+    lines: Vec<Coords3>,
+}
+
+impl Cube {
+    pub fn new(size: usize, win_condition: usize) -> anyhow::Result<Self> {
+        ensure!(size > 0, "A cube needs at least one cell per side");
+        ensure!(
+            (2..=size).contains(&win_condition),
+            "The win condition must be between 2 and the size of the cube, {}, not {}",
+            size,
+            win_condition
+        );
+        Ok(Self {
+            grid: vec![vec![vec![CellState::Empty; size]; size]; size],
+            size,
+            win_condition,
+            turn: CellState::X,
+            lines: calculate_lines_coords(size, win_condition),
+        })
+    }
+
+    /// Cube of the dimensions of a square board config, whose rules besides the dimensions only
+    /// apply to flat boards
+    pub fn from_config(config: BoardConfig) -> anyhow::Result<Self> {
+        ensure!(
+            config.rows == config.columns,
+            "The cube needs a square board, not {} rows and {} columns",
+            config.rows,
+            config.columns
+        );
+        ensure!(
+            config.variant == Variant::Standard && !config.gravity && !config.toroidal,
+            "The cube is only played with the standard rules, not the {}",
+            config
+        );
+        Self::new(config.rows, config.win_condition)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn reset(&mut self) {
+        self.grid = vec![vec![vec![CellState::Empty; self.size]; self.size]; self.size];
+        self.turn = CellState::X;
+    }
+
+    pub fn is_valid_move(&self, x_axis: usize, y_axis: usize, layer: usize) -> bool {
+        x_axis < self.size
+            && y_axis < self.size
+            && layer < self.size
+            && self.grid[layer][x_axis][y_axis] == CellState::Empty
+    }
+
+    pub fn play_move(&mut self, x_axis: usize, y_axis: usize, layer: usize) {
+        if self.is_valid_move(x_axis, y_axis, layer) {
+            self.grid[layer][x_axis][y_axis] = self.turn;
+            self.turn = self.turn.opponent();
+        }
+    }
+
+    pub fn get_current_player(&self) -> CellState {
+        self.turn
+    }
+
+    pub fn is_board_full(&self) -> bool {
+        self.grid
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|&cell| cell != CellState::Empty)
+    }
+
+    /// Returns the winner of the game if there's any
+    pub fn is_winner(&self) -> Option<CellState> {
+        self.lines.iter().find_map(|line| {
+            let cells = line
+                .iter()
+                .map(|&(x_axis, y_axis, layer)| self.grid[layer][x_axis][y_axis]);
+            find_line(cells, self.win_condition)
+        })
+    }
+
+    pub fn get_possible_actions(&self) -> Vec<Action> {
+        let mut possible_actions = Vec::new();
+        for layer in 0..self.size {
+            for x_axis in 0..self.size {
+                for y_axis in 0..self.size {
+                    if self.grid[layer][x_axis][y_axis] == CellState::Empty {
+                        possible_actions.push(Action {
+                            x_axis,
+                            y_axis,
+                            layer,
+                            symbol: None,
+                        });
+                    }
+                }
+            }
+        }
+        possible_actions
+    }
+
+    /// Layers side by side along the x axis, so a cube fits in a `State`
+    pub fn get_grid(&self) -> State {
+        self.grid.iter().flatten().cloned().collect()
+    }
+}
+
+impl Game for Cube {
+    fn reset(&mut self) {
+        Cube::reset(self);
+    }
+
+    fn play(&mut self, action: Action) {
+        self.play_move(action.x_axis, action.y_axis, action.layer);
+    }
+
+    fn winner(&self) -> Option<CellState> {
+        self.is_winner()
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_board_full()
+    }
+
+    fn possible_actions(&self) -> Vec<Action> {
+        self.get_possible_actions()
+    }
+
+    fn state(&self) -> State {
+        self.get_grid()
+    }
+
+    fn current_player(&self) -> CellState {
+        self.turn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qubic_has_76_lines() {
+        // 48 along the axes, 24 plane diagonals and 4 space diagonals
+        assert_eq!(calculate_lines_coords(4, 4).len(), 76);
+        assert_eq!(calculate_lines_coords(3, 3).len(), 49);
+        let cube = Cube::new(4, 4).unwrap();
+        assert_eq!(cube.get_possible_actions().len(), 64);
+        assert!(Cube::new(3, 4).is_err());
+        assert_eq!(
+            Cube::from_config(BoardConfig::square(4, 3)).unwrap().size(),
+            4
+        );
+        let rectangle = BoardConfig {
+            rows: 3,
+            ..BoardConfig::square(4, 3)
+        };
+        assert!(Cube::from_config(rectangle).is_err());
+    }
+
+    #[test]
+    fn test_space_diagonal_wins() {
+        let mut cube = Cube::new(3, 3).unwrap();
+        for (x_axis, y_axis, layer) in [(0, 0, 0), (0, 1, 0), (1, 1, 1), (0, 2, 0)] {
+            cube.play_move(x_axis, y_axis, layer);
+        }
+        assert_eq!(cube.is_winner(), None);
+        cube.play_move(2, 2, 2);
+        assert_eq!(cube.is_winner(), Some(CellState::X));
+        assert!(!cube.is_valid_move(1, 1, 1));
+        assert_eq!(cube.get_grid().len(), 9);
+    }
+}
@@ -0,0 +1,331 @@
+use ndarray::{Array1, Array2};
+use rand::RngCore;
+
+use crate::{
+    agent::{Action, Agent, Environment, Reward},
+    logic::{Board, BoardConfig, CellState},
+    nn::Mlp,
+    policy::Policy,
+    replay::{ReplayBuffer, ReplayConfig},
+};
+
+/// Number of one-hot channels per cell: empty, player to move, opponent
+const N_CHANNELS: usize = 3;
+
+/// Settings of the deep Q-learning agent
+#[derive(Clone, Debug)]
+pub struct DqnConfig {
+    pub hidden_layers: Vec<usize>,
+    pub learning_rate: f64,
+    pub gamma: f64,
+    pub epsilon_start: f64,
+    pub epsilon_end: f64,
+    pub replay: ReplayConfig,
+    /// Number of gradient steps between two copies of the online network into the target
+    pub target_update_every: usize,
+    /// Position the games start from instead of the empty board, see `Board::from_position`
+    pub start: Option<String>,
+}
+
+impl Default for DqnConfig {
+    fn default() -> Self {
+        Self {
+            hidden_layers: vec![64, 64],
+            learning_rate: 1e-3,
+            gamma: 0.95,
+            epsilon_start: 1.0,
+            epsilon_end: 0.05,
+            replay: ReplayConfig::default(),
+            target_update_every: 250,
+            start: None,
+        }
+    }
+}
+
+/// Encodes the grid as one-hot cells, relative to the player to move so the same network plays
+/// both sides
+pub fn encode(grid: &[Vec<CellState>], player: CellState) -> Array1<f64> {
+    let mut input = Array1::zeros(grid.len() * grid[0].len() * N_CHANNELS);
+    for (index, cell) in grid.iter().flatten().enumerate() {
+        let channel = match *cell {
+            CellState::Empty => 0,
+            // Obstacles are left out of every channel
+            CellState::Blocked => continue,
+            cell if cell == player => 1,
+            _ => 2,
+        };
+        input[index * N_CHANNELS + channel] = 1.0;
+    }
+    input
+}
+
+/// Transition with the positions already encoded for the network
+struct EncodedTransition {
+    state: Array1<f64>,
+    action: usize,
+    reward: f64,
+    next_state: Array1<f64>,
+    next_actions: Vec<usize>,
+    done: bool,
+}
+
+/// Agent approximating the Q-values of every cell with a neural network
+pub struct DqnAgent {
+    online: Mlp,
+    target: Mlp,
+    /// Length of a column of the grid, actions are indexed column after column
+    rows: usize,
+    policy: Policy,
+}
+
+impl DqnAgent {
+    pub fn new(board: BoardConfig, config: &DqnConfig, rng: &mut dyn RngCore) -> Self {
+        let mut layer_sizes = vec![board.n_cells() * N_CHANNELS];
+        layer_sizes.extend(&config.hidden_layers);
+        layer_sizes.push(board.n_cells());
+        let online = Mlp::new(&layer_sizes, rng);
+        Self {
+            target: online.clone(),
+            online,
+            rows: board.rows,
+            policy: Policy::Greedy,
+        }
+    }
+
+    fn action_index(&self, action: Action) -> usize {
+        action.x_axis * self.rows + action.y_axis
+    }
+
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+    }
+
+    /// Chooses among the legal actions from their predicted Q-values, no visit counts are kept
+    /// so the UCB policy degrades to greedy
+    pub fn search(&self, board: &Board, policy: &Policy, rng: &mut dyn RngCore) -> Action {
+        let (possible_actions, q_values): (Vec<Action>, Vec<f64>) =
+            self.q_values(board).into_iter().unzip();
+        let visits = vec![0; possible_actions.len()];
+        possible_actions[policy.select(&q_values, &visits, rng)]
+    }
+
+    /// Predicted Q-value of every legal move
+    fn q_values(&self, board: &Board) -> Vec<(Action, f64)> {
+        let predictions = self
+            .online
+            .predict(&encode(&board.get_grid(), board.get_current_player()));
+        Action::possible_actions(board)
+            .into_iter()
+            .map(|action| (action, predictions[self.action_index(action)]))
+            .collect()
+    }
+
+    /// Fits the online network on a minibatch, with targets computed by the target network.
+    /// Returns the loss and the TD error of every transition.
+    fn learn(
+        &mut self,
+        batch: &[&EncodedTransition],
+        weights: &[f64],
+        config: &DqnConfig,
+    ) -> (f64, Vec<f64>) {
+        let n_inputs = batch[0].state.len();
+        let mut states = Array2::zeros((batch.len(), n_inputs));
+        let mut next_states = Array2::zeros((batch.len(), n_inputs));
+        for (row, transition) in batch.iter().enumerate() {
+            states.row_mut(row).assign(&transition.state);
+            next_states.row_mut(row).assign(&transition.next_state);
+        }
+
+        let mut targets = self.online.forward(&states);
+        let next_q_values = self.target.forward(&next_states);
+        let mut td_errors = Vec::with_capacity(batch.len());
+        for (row, transition) in batch.iter().enumerate() {
+            let max_next_q = if transition.done {
+                0.0
+            } else {
+                transition
+                    .next_actions
+                    .iter()
+                    .map(|&action| next_q_values[[row, action]])
+                    .fold(f64::NEG_INFINITY, f64::max)
+            };
+            let target = transition.reward + config.gamma * max_next_q;
+            td_errors.push(target - targets[[row, transition.action]]);
+            targets[[row, transition.action]] = target;
+        }
+        let loss = self.online.train_step(
+            &states,
+            &targets,
+            &Array1::from_vec(weights.to_vec()),
+            config.learning_rate,
+        );
+        (loss, td_errors)
+    }
+}
+
+impl Agent for DqnAgent {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        self.search(board, &self.policy, rng)
+    }
+
+    fn action_values(&self, board: &Board) -> Option<Vec<(Action, f64)>> {
+        Some(self.q_values(board))
+    }
+}
+
+/// Trains a DQN agent against the given opponent, alternating sides every game. Transitions go
+/// from one position of the learner to its next one, after the opponent replied.
+pub fn train_dqn(
+    n_games: usize,
+    board: BoardConfig,
+    config: &DqnConfig,
+    opponent: &mut dyn Agent,
+    rng: &mut dyn RngCore,
+) -> DqnAgent {
+    let mut agent = DqnAgent::new(board, config, rng);
+    let start = Board::from_start(config.start.as_deref(), board)
+        .expect("The start position was checked with the experiment");
+    let mut env = Environment::from_game(start, CellState::X);
+    let mut replay = ReplayBuffer::new(config.replay.capacity, config.replay.sampling);
+    let mut n_steps = 0;
+    let mut losses = Vec::new();
+
+    for game in 0..n_games {
+        let progress = game as f64 / n_games as f64;
+        let epsilon = config.epsilon_start + (config.epsilon_end - config.epsilon_start) * progress;
+        env.reset();
+        env.player = if game % 2 == 0 {
+            CellState::X
+        } else {
+            CellState::O
+        };
+
+        let mut reward = Reward::INTERMEDIATE;
+        if env.get_player() != env.player {
+            let action = opponent.select_action(&env.board, rng);
+            (_, reward) = env.step(action);
+        }
+
+        while reward == Reward::INTERMEDIATE {
+            let state = encode(&env.get_grid(), env.player);
+            let action = agent.search(&env.board, &Policy::EpsilonGreedy { epsilon }, rng);
+            (_, reward) = env.step(action);
+            if reward == Reward::INTERMEDIATE {
+                let opponent_action = opponent.select_action(&env.board, rng);
+                (_, reward) = env.step(opponent_action);
+            }
+
+            replay.push(EncodedTransition {
+                state,
+                action: agent.action_index(action),
+                reward: reward as f64,
+                next_state: encode(&env.get_grid(), env.player),
+                next_actions: env
+                    .get_possibe_moves()
+                    .iter()
+                    .map(|&next_action| agent.action_index(next_action))
+                    .collect(),
+                done: reward != Reward::INTERMEDIATE,
+            });
+
+            if let Some(batch) = replay.sample(config.replay.batch_size, rng) {
+                let transitions: Vec<&EncodedTransition> = batch
+                    .indices
+                    .iter()
+                    .map(|&index| replay.get(index))
+                    .collect();
+                let (loss, td_errors) = agent.learn(&transitions, &batch.weights, config);
+                for (&index, td_error) in batch.indices.iter().zip(td_errors) {
+                    replay.update_priority(index, td_error);
+                }
+                losses.push(loss);
+                n_steps += 1;
+                if n_steps % config.target_update_every == 0 {
+                    agent.target = agent.online.clone();
+                }
+            }
+        }
+
+        if (game + 1) % 1000 == 0 {
+            println!(
+                "Game: {}, mean loss: {}, epsilon: {}",
+                game + 1,
+                losses.iter().sum::<f64>() / losses.len().max(1) as f64,
+                epsilon
+            );
+            losses.clear();
+        }
+    }
+    agent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::RandomAgent, replay::Sampling};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_encode_is_relative_to_player() {
+        let mut board = Board::new(BoardConfig::default());
+        board.play_move(0, 0);
+        let grid = board.get_grid();
+        let for_x = encode(&grid, CellState::X);
+        let for_o = encode(&grid, CellState::O);
+        assert_eq!(for_x.len(), 27);
+        assert_eq!(for_x.sum(), 9.0);
+        assert_eq!(for_x[1], 1.0);
+        assert_eq!(for_o[2], 1.0);
+    }
+
+    #[test]
+    fn test_action_indices_cover_rectangular_board() {
+        let config = BoardConfig {
+            rows: 2,
+            columns: 3,
+            win_condition: 2,
+            ..BoardConfig::default()
+        };
+        let agent = DqnAgent::new(
+            config,
+            &DqnConfig::default(),
+            &mut ChaCha8Rng::seed_from_u64(0),
+        );
+        let board = Board::new(config);
+        let mut indices: Vec<usize> = Action::possible_actions(&board)
+            .into_iter()
+            .map(|action| agent.action_index(action))
+            .collect();
+        indices.sort();
+        assert_eq!(indices, (0..6).collect::<Vec<_>>());
+        assert_eq!(agent.q_values(&board).len(), 6);
+    }
+
+    #[test]
+    fn test_dqn_plays_legal_moves() {
+        let config = DqnConfig {
+            replay: ReplayConfig {
+                batch_size: 8,
+                sampling: Sampling::Prioritized {
+                    alpha: 0.6,
+                    beta: 0.4,
+                },
+                ..ReplayConfig::default()
+            },
+            ..DqnConfig::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut agent = train_dqn(
+            20,
+            BoardConfig::default(),
+            &config,
+            &mut RandomAgent,
+            &mut rng,
+        );
+        let mut board = Board::new(BoardConfig::default());
+        board.play_move(1, 1);
+        let action = agent.select_action(&board, &mut rng);
+        assert!(board.is_valid_move(action.x_axis, action.y_axis));
+    }
+}
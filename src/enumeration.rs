@@ -0,0 +1,222 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    agent::{Action, State},
+    logic::{Board, BoardConfig, CellState},
+};
+
+/// Positions of a ply, terminal ones are counted by outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlyCounts {
+    pub positions: usize,
+    pub x_wins: usize,
+    pub o_wins: usize,
+    pub draws: usize,
+}
+
+impl PlyCounts {
+    pub fn terminal(&self) -> usize {
+        self.x_wins + self.o_wins + self.draws
+    }
+}
+
+/// Every position reachable from the empty board, deduplicated
+pub struct Enumeration {
+    /// Counts of every ply, the index being the number of moves played
+    pub plies: Vec<PlyCounts>,
+    /// Non terminal positions, only their canonical form when symmetries are merged
+    pub states: HashSet<State>,
+}
+
+impl Enumeration {
+    pub fn total(&self) -> PlyCounts {
+        self.plies
+            .iter()
+            .fold(PlyCounts::default(), |total, ply| PlyCounts {
+                positions: total.positions + ply.positions,
+                x_wins: total.x_wins + ply.x_wins,
+                o_wins: total.o_wins + ply.o_wins,
+                draws: total.draws + ply.draws,
+            })
+    }
+}
+
+impl fmt::Display for Enumeration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>5} {:>12} {:>12} {:>10} {:>10} {:>10}",
+            "Ply", "Positions", "Non terminal", "X wins", "O wins", "Draws"
+        )?;
+        let row = |f: &mut fmt::Formatter, ply: &str, counts: &PlyCounts| {
+            writeln!(
+                f,
+                "{:>5} {:>12} {:>12} {:>10} {:>10} {:>10}",
+                ply,
+                counts.positions,
+                counts.positions - counts.terminal(),
+                counts.x_wins,
+                counts.o_wins,
+                counts.draws
+            )
+        };
+        for (ply, counts) in self.plies.iter().enumerate() {
+            row(f, &ply.to_string(), counts)?;
+        }
+        row(f, "Total", &self.total())
+    }
+}
+
+/// The 8 rotations and reflections of a square grid, or the 4 of a rectangular one
+pub fn symmetries(state: &State) -> Vec<State> {
+    let size = state.len();
+    let flip = |grid: &State| -> State { grid.iter().rev().cloned().collect() };
+    if state[0].len() != size {
+        let flip_rows = |grid: &State| -> State {
+            grid.iter()
+                .map(|column| column.iter().rev().cloned().collect())
+                .collect()
+        };
+        return vec![
+            state.clone(),
+            flip(state),
+            flip_rows(state),
+            flip(&flip_rows(state)),
+        ];
+    }
+    let transpose = |grid: &State| -> State {
+        (0..size)
+            .map(|x_axis| (0..size).map(|y_axis| grid[y_axis][x_axis]).collect())
+            .collect()
+    };
+    let mut transforms = Vec::with_capacity(8);
+    let mut grid = state.clone();
+    for _ in 0..4 {
+        // Transposing then flipping rotates by a quarter turn
+        transforms.push(transpose(&grid));
+        grid = flip(&transpose(&grid));
+        transforms.push(grid.clone());
+    }
+    transforms
+}
+
+/// Smallest of the symmetric forms of the state, equal for all the states of a symmetry class
+pub fn canonical(state: &State) -> State {
+    symmetries(state).into_iter().min().unwrap()
+}
+
+/// Walks every position reachable from the empty board, stopping at terminal ones. Returns
+/// `None` when there are more than `max_states` non terminal positions.
+pub fn enumerate(
+    config: BoardConfig,
+    merge_symmetries: bool,
+    max_states: usize,
+) -> Option<Enumeration> {
+    let key = |board: &Board| {
+        if merge_symmetries && config.gravity {
+            // Marks lie on the bottom row, so only the mirror image keeps the position legal
+            let grid = board.get_grid();
+            let mirror: State = grid.iter().rev().cloned().collect();
+            grid.min(mirror)
+        } else if merge_symmetries {
+            canonical(&board.get_grid())
+        } else {
+            board.get_grid()
+        }
+    };
+    let mut enumeration = Enumeration {
+        plies: Vec::new(),
+        states: HashSet::new(),
+    };
+    let mut frontier = vec![Board::new(config)];
+    // Every move adds a symbol, so positions of different plies never collide
+    while !frontier.is_empty() {
+        let mut counts = PlyCounts {
+            positions: frontier.len(),
+            ..PlyCounts::default()
+        };
+        let mut next_frontier = Vec::new();
+        let mut next_keys = HashSet::new();
+        for board in frontier {
+            match board.is_winner() {
+                Some(CellState::X) => counts.x_wins += 1,
+                Some(_) => counts.o_wins += 1,
+                None if board.is_board_full() => counts.draws += 1,
+                None => {
+                    enumeration.states.insert(key(&board));
+                    if enumeration.states.len() > max_states {
+                        return None;
+                    }
+                    for action in Action::possible_actions(&board) {
+                        let mut next_board = board.clone();
+                        next_board.play_move(action.x_axis, action.y_axis);
+                        if next_keys.insert(key(&next_board)) {
+                            next_frontier.push(next_board);
+                        }
+                    }
+                }
+            }
+        }
+        enumeration.plies.push(counts);
+        frontier = next_frontier;
+    }
+    Some(enumeration)
+}
+
+/// Every non terminal position reachable from the empty board, or `None` when there are more
+/// than `max_states` of them
+pub fn reachable_states(config: BoardConfig, max_states: usize) -> Option<HashSet<State>> {
+    enumerate(config, false, max_states).map(|enumeration| enumeration.states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_states_3x3() {
+        // 5478 legal positions, 958 of them are terminal
+        let states = reachable_states(BoardConfig::default(), usize::MAX).unwrap();
+        assert_eq!(states.len(), 5478 - 958);
+        assert!(reachable_states(BoardConfig::default(), 100).is_none());
+    }
+
+    #[test]
+    fn test_enumerate_3x3() {
+        let total = enumerate(BoardConfig::default(), false, usize::MAX)
+            .unwrap()
+            .total();
+        assert_eq!(
+            total,
+            PlyCounts {
+                positions: 5478,
+                x_wins: 626,
+                o_wins: 316,
+                draws: 16,
+            }
+        );
+        let enumeration = enumerate(BoardConfig::default(), true, usize::MAX).unwrap();
+        assert_eq!(enumeration.plies[1].positions, 3);
+        assert_eq!(
+            enumeration.total(),
+            PlyCounts {
+                positions: 765,
+                x_wins: 91,
+                o_wins: 44,
+                draws: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_symmetries_are_distinct_for_asymmetric_state() {
+        let board = Board::from_position("XO./.../...", BoardConfig::default()).unwrap();
+        let forms: HashSet<State> = symmetries(&board.get_grid()).into_iter().collect();
+        assert_eq!(forms.len(), 8);
+
+        let board = Board::from_position("XO./...", BoardConfig::default()).unwrap();
+        let forms: HashSet<State> = symmetries(&board.get_grid()).into_iter().collect();
+        assert_eq!(forms.len(), 4);
+        assert!(forms.contains(&board.get_grid()));
+    }
+}
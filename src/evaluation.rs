@@ -0,0 +1,149 @@
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    agent::{Agent, Game, QTable},
+    logic::{Board, CellState},
+};
+
+/// Outcomes of a series of games, from the point of view of the evaluated agent
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EvaluationResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl EvaluationResult {
+    pub fn n_games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average score where a win counts 1 and a draw 0.5
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.n_games().max(1) as f64
+    }
+}
+
+/// Plays a game to the end from the given position and returns the winner if there's any
+pub fn play_game(
+    board: &mut Board,
+    x_agent: &mut dyn Agent,
+    o_agent: &mut dyn Agent,
+    rng: &mut dyn RngCore,
+) -> Option<CellState> {
+    loop {
+        if let Some(winner) = board.is_winner() {
+            return Some(winner);
+        }
+        if board.is_board_full() {
+            return None;
+        }
+        let action = match board.get_current_player() {
+            CellState::X => x_agent.select_action(board, rng),
+            _ => o_agent.select_action(board, rng),
+        };
+        board.play_move(action.x_axis, action.y_axis);
+    }
+}
+
+/// Plays `n_games` from the `start` board between the agent and the opponent, swapping sides
+/// after every game
+pub fn evaluate(
+    agent: &mut dyn Agent,
+    opponent: &mut dyn Agent,
+    n_games: usize,
+    start: &Board,
+    rng: &mut dyn RngCore,
+) -> EvaluationResult {
+    let mut result = EvaluationResult::default();
+    let mut board = start.clone();
+    for game in 0..n_games {
+        board.reset();
+        let agent_side = if game % 2 == 0 {
+            CellState::X
+        } else {
+            CellState::O
+        };
+        let winner = if agent_side == CellState::X {
+            play_game(&mut board, agent, opponent, rng)
+        } else {
+            play_game(&mut board, opponent, agent, rng)
+        };
+        match winner {
+            Some(winner) if winner == agent_side => result.wins += 1,
+            Some(_) => result.losses += 1,
+            None => result.draws += 1,
+        }
+    }
+    result
+}
+
+/// Plays `n_games` of the greedy table against a uniformly random player on any game, swapping
+/// sides after every game
+pub(crate) fn evaluate_against_random<G: Game>(
+    table: &QTable,
+    mut game: G,
+    n_games: usize,
+    rng: &mut dyn RngCore,
+) -> EvaluationResult {
+    let mut result = EvaluationResult::default();
+    for index in 0..n_games {
+        game.reset();
+        let agent_side = if index % 2 == 0 {
+            CellState::X
+        } else {
+            CellState::O
+        };
+        while game.winner().is_none() && !game.is_full() {
+            let action = if game.current_player() == agent_side {
+                table.greedy_action(&game, rng)
+            } else {
+                *game.possible_actions().choose(rng).unwrap()
+            };
+            game.play(action);
+        }
+        match game.winner() {
+            Some(winner) if winner == agent_side => result.wins += 1,
+            Some(_) => result.losses += 1,
+            None => result.draws += 1,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{HeuristicAgent, RandomAgent},
+        logic::BoardConfig,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_evaluate_counts_every_game() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let result = evaluate(
+            &mut RandomAgent,
+            &mut RandomAgent,
+            100,
+            &Board::new(BoardConfig::default()),
+            &mut rng,
+        );
+        assert_eq!(result.n_games(), 100);
+    }
+
+    #[test]
+    fn test_heuristic_beats_random() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let result = evaluate(
+            &mut HeuristicAgent,
+            &mut RandomAgent,
+            200,
+            &Board::new(BoardConfig::default()),
+            &mut rng,
+        );
+        assert!(result.wins > result.losses);
+    }
+}
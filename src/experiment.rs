@@ -0,0 +1,359 @@
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, path::PathBuf, time::Duration};
+
+use crate::{
+    agent::{AgentSpec, Rewards, Schedule, TrainingOptions},
+    checkpoint::{load_json, save_json, CheckpointConfig},
+    convergence::EarlyStopping,
+    dqn::DqnConfig,
+    league::League,
+    logic::{Board, BoardConfig},
+    mcts::Budget,
+    policy::{Exploration, Policy},
+    replay::ReplayConfig,
+};
+
+/// Everything a training run depends on, saved as JSON so a run can be repeated and its outputs
+/// traced back to it. Missing fields take their default value.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    /// Free text telling experiments apart
+    pub name: Option<String>,
+    pub environment: BoardConfig,
+    /// Position every game starts from instead of the empty board, see `Board::from_position`
+    pub start: Option<String>,
+    /// Agent played against in the GUI and evaluated by the CLI
+    pub agent: AgentConfig,
+    pub learner: LearnerConfig,
+    pub schedule: Schedule,
+    pub rewards: Rewards,
+    /// Opponents of the learner, it plays against itself when missing
+    pub opponents: Option<League>,
+    /// Seed of the random generator, drawn from the system when missing
+    pub seed: Option<u64>,
+    pub outputs: OutputConfig,
+}
+
+/// Kind of agent of the experiment, the Q-learning agent is trained with the learner settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentConfig {
+    Random,
+    Heuristic,
+    #[default]
+    QTable,
+    /// Q-table loaded from a file instead of trained
+    SavedQTable {
+        path: PathBuf,
+    },
+    Dqn {
+        n_games: usize,
+    },
+    Mcts {
+        iterations: usize,
+        /// Time limit per move, takes precedence over the iterations
+        #[serde(default)]
+        time_ms: Option<u64>,
+        #[serde(default)]
+        heuristic_rollouts: bool,
+    },
+    Minimax {
+        depth: usize,
+    },
+}
+
+/// Settings of the Q-learning training
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LearnerConfig {
+    pub n_epoch: usize,
+    /// Number of games per epoch
+    pub n_games: usize,
+    pub exploration: Exploration,
+    pub replay: Option<ReplayConfig>,
+    pub early_stopping: Option<EarlyStopping>,
+    /// Policy of the trained agent once it plays
+    pub play_policy: Policy,
+}
+
+impl Default for LearnerConfig {
+    fn default() -> Self {
+        Self {
+            n_epoch: 200,
+            n_games: 1000,
+            exploration: Exploration::default(),
+            replay: None,
+            early_stopping: None,
+            play_policy: Policy::Greedy,
+        }
+    }
+}
+
+/// Files written by the experiment
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Trained Q-table
+    pub table: PathBuf,
+    /// Metrics of every epoch
+    pub metrics: Option<PathBuf>,
+    pub checkpoint: Option<CheckpointConfig>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            table: PathBuf::from("q_table.json"),
+            metrics: None,
+            checkpoint: None,
+        }
+    }
+}
+
+impl ExperimentConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let config: Self = load_json(path)
+            .with_context(|| format!("Invalid experiment config {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid experiment config {}", path.display()))?;
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        save_json(self, path)
+    }
+
+    /// Options of the training, without the checkpoints only written by the run of
+    /// `TrainingState::from_experiment`
+    pub fn training_options(&self) -> TrainingOptions {
+        TrainingOptions {
+            replay: self.learner.replay,
+            exploration: self.learner.exploration,
+            league: self.opponents.clone(),
+            seed: self.seed,
+            checkpoint: None,
+            early_stopping: self.learner.early_stopping.clone(),
+            schedule: self.schedule.clone(),
+            rewards: self.rewards,
+            start: self.start.clone(),
+            quiet: false,
+        }
+    }
+
+    /// Checks the settings a run can't be started with
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.environment
+            .validate()
+            .context("Invalid board configuration")?;
+        self.schedule.validate()?;
+        if let Some(replay) = &self.learner.replay {
+            replay.validate()?;
+        }
+        if let Some(league) = &self.opponents {
+            league.validate()?;
+        }
+        if let Some(plateau) = self
+            .learner
+            .early_stopping
+            .as_ref()
+            .and_then(|config| config.plateau.as_ref())
+        {
+            plateau.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Board the games start from, checked against the environment
+    pub fn start_board(&self) -> anyhow::Result<Board> {
+        let board = Board::from_start(self.start.as_deref(), self.environment)?;
+        ensure!(
+            board.config() == self.environment,
+            "Start position `{}` doesn't fit the {}",
+            self.start.as_deref().unwrap_or_default(),
+            self.environment
+        );
+        Ok(board)
+    }
+
+    pub fn agent_spec(&self) -> AgentSpec {
+        let policy = self.learner.play_policy;
+        match &self.agent {
+            AgentConfig::Random => AgentSpec::Random,
+            AgentConfig::Heuristic => AgentSpec::Heuristic,
+            AgentConfig::QTable => AgentSpec::QTable {
+                n_games: self.learner.n_games,
+                n_epoch: self.learner.n_epoch,
+                options: Box::new(self.training_options()),
+                policy,
+            },
+            AgentConfig::SavedQTable { path } => AgentSpec::SavedQTable {
+                path: path.clone(),
+                policy,
+            },
+            AgentConfig::Dqn { n_games } => AgentSpec::Dqn {
+                n_games: *n_games,
+                config: DqnConfig {
+                    replay: self.learner.replay.unwrap_or_default(),
+                    start: self.start.clone(),
+                    ..DqnConfig::default()
+                },
+                policy,
+            },
+            AgentConfig::Mcts {
+                iterations,
+                time_ms,
+                heuristic_rollouts,
+            } => AgentSpec::Mcts {
+                budget: match time_ms {
+                    Some(time_ms) => Budget::Time(Duration::from_millis(*time_ms)),
+                    None => Budget::Iterations(*iterations),
+                },
+                heuristic_rollouts: *heuristic_rollouts,
+            },
+            AgentConfig::Minimax { depth } => AgentSpec::Minimax { depth: *depth },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{QTable, TrainingState},
+        convergence::Plateau,
+        league::OpponentKind,
+        logic::CellState,
+    };
+
+    #[test]
+    fn test_partial_config_takes_defaults() {
+        let config: ExperimentConfig = serde_json::from_str(
+            r#"{
+                "environment": {"rows": 4, "columns": 5},
+                "agent": {"type": "minimax", "depth": 2},
+                "learner": {"n_epoch": 10},
+                "rewards": {"win": 1.0, "draw": 0.5, "loss": -1.0},
+                "seed": 7
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.environment.rows, 4);
+        assert_eq!(config.environment.columns, 5);
+        assert_eq!(config.environment.win_condition, 3);
+        assert_eq!(config.learner.n_epoch, 10);
+        assert_eq!(config.learner.n_games, 1000);
+        assert!(matches!(
+            config.agent_spec(),
+            AgentSpec::Minimax { depth: 2 }
+        ));
+        let options = config.training_options();
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.rewards.draw, 0.5);
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExperimentConfig>(&json).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn test_square_board_size_still_loads() {
+        let config: ExperimentConfig =
+            serde_json::from_str(r#"{"environment": {"board_size": 4, "win_condition": 4}}"#)
+                .unwrap();
+        assert_eq!(config.environment, BoardConfig::square(4, 4));
+    }
+
+    #[test]
+    fn test_empty_schedule_is_rejected() {
+        let config: ExperimentConfig = serde_json::from_str(
+            r#"{"schedule": {"alpha": [], "gamma": [0.9], "epsilon": [0.1]}}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+        assert!(ExperimentConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_unplayable_settings_are_rejected() {
+        let config = ExperimentConfig {
+            environment: BoardConfig::square(3, 4),
+            ..ExperimentConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = ExperimentConfig {
+            opponents: Some(League {
+                opponents: vec![(OpponentKind::Snapshot, 1.0)],
+                snapshot_every: 1,
+                max_snapshots: 0,
+            }),
+            ..ExperimentConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = ExperimentConfig {
+            learner: LearnerConfig {
+                early_stopping: Some(EarlyStopping {
+                    plateau: Some(Plateau {
+                        opponent: OpponentKind::Snapshot,
+                        n_games: 10,
+                        min_delta: 0.01,
+                    }),
+                    ..EarlyStopping::default()
+                }),
+                ..LearnerConfig::default()
+            },
+            ..ExperimentConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_start_position_fits_the_environment() {
+        let mut config = ExperimentConfig {
+            start: Some("X#./.O./...".to_string()),
+            ..ExperimentConfig::default()
+        };
+        let board = config.start_board().unwrap();
+        assert_eq!(board.get_grid()[1][0], CellState::Blocked);
+        assert_eq!(board.get_current_player(), CellState::X);
+
+        config.start = Some("..../..../..../....".to_string());
+        assert!(config.start_board().is_err());
+    }
+
+    #[test]
+    fn test_finished_start_position_is_rejected() {
+        // Won, full, and without any empty cell
+        for position in ["XXX/OO./...", "XOX/XOO/OXX", "###/###/###"] {
+            let config = ExperimentConfig {
+                start: Some(position.to_string()),
+                ..ExperimentConfig::default()
+            };
+            assert!(config.start_board().is_err(), "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_trained_table_is_stamped() {
+        let config = ExperimentConfig {
+            name: Some("stamped".to_string()),
+            learner: LearnerConfig {
+                n_epoch: 1,
+                n_games: 10,
+                ..LearnerConfig::default()
+            },
+            seed: Some(0),
+            ..ExperimentConfig::default()
+        };
+        let table = TrainingState::from_experiment(&config).run().into_agent();
+        let json = serde_json::to_string(&table).unwrap();
+        let loaded: QTable = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.experiment(), Some(&config));
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::ensure;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    agent::{Action, Agent, QTable, State},
+    logic::{Board, BoardConfig, CellState},
+};
+
+/// Move of the policy that loses value against a perfect adversary
+pub struct Deviation {
+    /// Position before the move, in the format of `Board::from_position`
+    pub position: String,
+    pub played: Action,
+    pub optimal: Vec<Action>,
+    /// Game value of the position for the policy and value after its move
+    pub values: (i32, f64),
+    /// Moves of both sides after the deviation, the adversary playing its best response
+    pub line: Vec<Action>,
+}
+
+/// Value a best responding adversary concedes to the policy, from the policy's point of view
+/// with a win counting 1, a draw 0 and a loss -1. Tied greedy moves are averaged.
+pub struct Exploitability {
+    /// Game value of the empty board for X under perfect play
+    pub game_value: i32,
+    pub value_as_x: f64,
+    pub value_as_o: f64,
+    pub deviations: Vec<Deviation>,
+}
+
+impl Exploitability {
+    /// Average value the policy gives away against a best response, over both sides
+    pub fn exploitability(&self) -> f64 {
+        ((self.game_value as f64 - self.value_as_x) + (-self.game_value as f64 - self.value_as_o))
+            / 2.0
+    }
+}
+
+impl fmt::Display for Exploitability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Game value for X: {}", self.game_value)?;
+        writeln!(
+            f,
+            "Best response value as X: {}, as O: {}, exploitability {}",
+            self.value_as_x,
+            self.value_as_o,
+            self.exploitability()
+        )?;
+        writeln!(f, "Deviations from optimal play: {}", self.deviations.len())?;
+        for deviation in &self.deviations {
+            let format = |actions: &[Action]| {
+                actions
+                    .iter()
+                    .map(|action| format!("({}, {})", action.x_axis, action.y_axis))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            writeln!(
+                f,
+                "  {}: played {}, optimal {}, value {} instead of {}, line {}",
+                deviation.position,
+                format(&[deviation.played]),
+                format(&deviation.optimal),
+                deviation.values.1,
+                deviation.values.0,
+                format(&deviation.line)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Full game-tree search of the positions reachable by the greedy policy of a table
+struct Solver<'a> {
+    table: &'a QTable,
+    max_states: usize,
+    /// Game value for the player to move
+    game_values: HashMap<State, i32>,
+    /// Best response value for the policy, per side of the policy
+    responses: HashMap<(State, CellState), f64>,
+}
+
+impl Solver<'_> {
+    fn check_size(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.game_values.len() + self.responses.len() <= self.max_states,
+            "More than {} positions to search",
+            self.max_states
+        );
+        Ok(())
+    }
+
+    /// Value of the position for the player who just moved, if the game is over
+    fn terminal_value(board: &Board) -> Option<i32> {
+        if let Some(winner) = board.is_winner() {
+            Some(if winner == board.get_current_player() {
+                -1
+            } else {
+                1
+            })
+        } else if board.is_board_full() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn after(board: &Board, action: Action) -> Board {
+        let mut next_board = board.clone();
+        next_board.play_move(action.x_axis, action.y_axis);
+        next_board
+    }
+
+    /// Negamax value of the position for the player to move
+    fn game_value(&mut self, board: &Board) -> anyhow::Result<i32> {
+        let state = board.get_grid();
+        if let Some(&value) = self.game_values.get(&state) {
+            return Ok(value);
+        }
+        self.check_size()?;
+        let mut best = -1;
+        for action in Action::possible_actions(board) {
+            let value = self.move_value(board, action)?;
+            best = best.max(value);
+            if best == 1 {
+                break;
+            }
+        }
+        self.game_values.insert(state, best);
+        Ok(best)
+    }
+
+    /// Value of playing the move for the player to move
+    fn move_value(&mut self, board: &Board, action: Action) -> anyhow::Result<i32> {
+        let next_board = Self::after(board, action);
+        match Self::terminal_value(&next_board) {
+            Some(value) => Ok(value),
+            None => Ok(-self.game_value(&next_board)?),
+        }
+    }
+
+    fn greedy_moves(&self, board: &Board) -> Vec<Action> {
+        let values = self.table.action_values(board).unwrap();
+        let best = values
+            .iter()
+            .map(|&(_, q)| q)
+            .fold(f64::NEG_INFINITY, f64::max);
+        values
+            .into_iter()
+            .filter(|&(_, q)| q == best)
+            .map(|(action, _)| action)
+            .collect()
+    }
+
+    /// Value for `policy` of the position when the adversary answers every move at best
+    fn response_value(&mut self, board: &Board, policy: CellState) -> anyhow::Result<f64> {
+        let key = (board.get_grid(), policy);
+        if let Some(&value) = self.responses.get(&key) {
+            return Ok(value);
+        }
+        self.check_size()?;
+        let value = if board.get_current_player() == policy {
+            let moves = self.greedy_moves(board);
+            let mut total = 0.0;
+            for &action in &moves {
+                total += self.response_move_value(board, action, policy)?;
+            }
+            total / moves.len() as f64
+        } else {
+            let mut worst = f64::INFINITY;
+            for action in Action::possible_actions(board) {
+                worst = worst.min(self.response_move_value(board, action, policy)?);
+            }
+            worst
+        };
+        self.responses.insert(key, value);
+        Ok(value)
+    }
+
+    fn response_move_value(
+        &mut self,
+        board: &Board,
+        action: Action,
+        policy: CellState,
+    ) -> anyhow::Result<f64> {
+        let next_board = Self::after(board, action);
+        match Self::terminal_value(&next_board) {
+            Some(value) if board.get_current_player() == policy => Ok(value as f64),
+            Some(value) => Ok(-value as f64),
+            None => self.response_value(&next_board, policy),
+        }
+    }
+
+    /// Game from the position, the policy playing its first greedy move and the adversary its
+    /// best response
+    fn line(&mut self, mut board: Board, policy: CellState) -> anyhow::Result<Vec<Action>> {
+        let mut line = Vec::new();
+        while Self::terminal_value(&board).is_none() {
+            let action = if board.get_current_player() == policy {
+                self.greedy_moves(&board)[0]
+            } else {
+                let mut best = None;
+                for action in Action::possible_actions(&board) {
+                    let value = self.response_move_value(&board, action, policy)?;
+                    if best.is_none_or(|(_, best_value)| value < best_value) {
+                        best = Some((action, value));
+                    }
+                }
+                best.unwrap().0
+            };
+            line.push(action);
+            board = Self::after(&board, action);
+        }
+        Ok(line)
+    }
+
+    /// Lists the moves of the policy losing value, in every position it can reach
+    fn deviations(
+        &mut self,
+        board: &Board,
+        policy: CellState,
+        visited: &mut HashSet<State>,
+        deviations: &mut Vec<Deviation>,
+    ) -> anyhow::Result<()> {
+        if Self::terminal_value(board).is_some() || !visited.insert(board.get_grid()) {
+            return Ok(());
+        }
+        let moves = if board.get_current_player() == policy {
+            let value = self.game_value(board)?;
+            let mut optimal = Vec::new();
+            for action in Action::possible_actions(board) {
+                if self.move_value(board, action)? == value {
+                    optimal.push(action);
+                }
+            }
+            let greedy = self.greedy_moves(board);
+            for &action in &greedy {
+                if !optimal.contains(&action) {
+                    let next_board = Self::after(board, action);
+                    let mut line = vec![action];
+                    line.extend(self.line(next_board, policy)?);
+                    deviations.push(Deviation {
+                        position: board.to_position(),
+                        played: action,
+                        optimal: optimal.clone(),
+                        values: (value, self.response_move_value(board, action, policy)?),
+                        line,
+                    });
+                }
+            }
+            greedy
+        } else {
+            Action::possible_actions(board)
+        };
+        for action in moves {
+            self.deviations(&Self::after(board, action), policy, visited, deviations)?;
+        }
+        Ok(())
+    }
+}
+
+/// Searches the whole game tree to find how much a best responding adversary can get from the
+/// greedy policy of the table, and where the policy deviates from optimal play. Fails when more
+/// than `max_states` positions would be searched.
+pub fn evaluate_exploitability(
+    table: &QTable,
+    config: BoardConfig,
+    max_states: usize,
+) -> anyhow::Result<Exploitability> {
+    let mut solver = Solver {
+        table,
+        max_states,
+        game_values: HashMap::new(),
+        responses: HashMap::new(),
+    };
+    let board = Board::new(config);
+    let mut deviations = Vec::new();
+    for policy in [CellState::X, CellState::O] {
+        solver.deviations(&board, policy, &mut HashSet::new(), &mut deviations)?;
+    }
+    // Costliest deviations first
+    let cost = |deviation: &Deviation| deviation.values.0 as f64 - deviation.values.1;
+    deviations.sort_by(|a, b| cost(b).total_cmp(&cost(a)));
+    Ok(Exploitability {
+        game_value: solver.game_value(&board)?,
+        value_as_x: solver.response_value(&board, CellState::X)?,
+        value_as_o: solver.response_value(&board, CellState::O)?,
+        deviations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        minimax::MinimaxAgent,
+        planning::{value_iteration, OpponentModel},
+    };
+
+    #[test]
+    fn test_empty_table_is_exploitable() {
+        let table = QTable::from_values(Vec::new(), 1.0);
+        let result = evaluate_exploitability(&table, BoardConfig::default(), usize::MAX).unwrap();
+        assert_eq!(result.game_value, 0);
+        assert!(result.value_as_x < 0.0 && result.value_as_o < 0.0);
+        assert!(!result.deviations.is_empty());
+        let deviation = &result.deviations[0];
+        assert!(!deviation.optimal.contains(&deviation.played));
+        assert!(deviation.values.1 < deviation.values.0 as f64);
+    }
+
+    #[test]
+    fn test_optimal_table_is_not_exploitable() {
+        let opponent = OpponentModel::Minimax(MinimaxAgent::new(9));
+        let plan =
+            value_iteration(BoardConfig::default(), &opponent, 1.0, 1e-9, usize::MAX).unwrap();
+        let result =
+            evaluate_exploitability(&plan.table, BoardConfig::default(), usize::MAX).unwrap();
+        assert_eq!(result.value_as_x, 0.0);
+        assert_eq!(result.value_as_o, 0.0);
+        assert!(result.deviations.is_empty());
+    }
+}
@@ -1,14 +1,26 @@
 use fltk::{
-    app, button::Button, draw, enums, frame::Frame, image::PngImage, input::Input, prelude::*,
+    app,
+    button::{Button, CheckButton, RadioRoundButton},
+    dialog, draw, enums,
+    frame::Frame,
+    image::PngImage,
+    input::Input,
+    prelude::*,
     window::Window,
 };
-// use std::path::Path;
 use std::cell::RefCell;
+use std::path::Path;
 use std::rc::Rc;
 
 use crate::{
-    agent::{train, Action, QTable},
-    logic::{Board, CellState},
+    agent::{Action, Agent, AgentSpec, Game, QTable},
+    cube::Cube,
+    experiment::ExperimentConfig,
+    inspect::shade,
+    logic::{Board, BoardConfig, CellState, Variant},
+    notakto::Notakto,
+    ultimate::{self, UltimateBoard},
+    wild::WildBoard,
 };
 
 pub struct TicTacToeApp {}
@@ -27,61 +39,149 @@ const DRAW_IMAGE: &[u8] = include_bytes!("../assets/draw.png");
 const EMPTY_IMAGE: &[u8] = &[];
 
 impl TicTacToeApp {
-    pub fn run(train_agent: bool) {
+    /// Opens the setup window filled with the environment and the start position, a config file
+    /// loaded from there replaces them along with the opponent
+    pub fn run(opponent: Option<AgentSpec>, environment: &BoardConfig, start: Option<&str>) {
         let app = app::App::default();
         // Setup stage
         let wind = Rc::new(RefCell::new(Window::new(
             100,
             100,
             400,
-            300,
+            385,
             "Tic Tac Toe Setup",
         )));
 
-        let mut board_size_input = Input::new(160, 50, 80, 30, "Board Size:");
-        board_size_input.set_value("3");
-
-        let mut win_condition_input = Input::new(160, 100, 80, 30, "Win Condition:");
-        win_condition_input.set_value("3");
-
-        let mut submit_button = Button::new(160, 150, 120, 60, "Start the game!");
+        let mut rows_input = Input::new(160, 30, 80, 30, "Rows:");
+        rows_input.set_value(&environment.rows.to_string());
+
+        let mut columns_input = Input::new(160, 70, 80, 30, "Columns:");
+        columns_input.set_value(&environment.columns.to_string());
+
+        let mut win_condition_input = Input::new(160, 110, 80, 30, "Win Condition:");
+        win_condition_input.set_value(&environment.win_condition.to_string());
+
+        // Empty for the empty board, otherwise it gives the dimensions, see `Board::from_position`
+        let mut position_input = Input::new(160, 150, 200, 30, "Start position:");
+        position_input.set_value(start.unwrap_or_default());
+        position_input.set_tooltip("Rows separated by /, # for blocked cells, e.g. X.O/.#./...:O");
+
+        let misere_check = CheckButton::new(160, 185, 120, 20, "Misère");
+        misere_check.set_checked(environment.variant == Variant::Misere);
+
+        let gravity_check = CheckButton::new(280, 185, 100, 20, "Gravity");
+        gravity_check.set_checked(environment.gravity);
+
+        let toroidal_check = CheckButton::new(160, 210, 120, 20, "Toroidal");
+        toroidal_check.set_checked(environment.toroidal);
+
+        let mut submit_button = Button::new(160, 240, 120, 60, "Start the game!");
+        let mut config_button = Button::new(160, 320, 120, 30, "Load config...");
+
+        let opponent = Rc::new(RefCell::new(opponent));
+        {
+            let mut rows_input = rows_input.clone();
+            let mut columns_input = columns_input.clone();
+            let mut win_condition_input = win_condition_input.clone();
+            let mut position_input = position_input.clone();
+            let misere_check = misere_check.clone();
+            let gravity_check = gravity_check.clone();
+            let toroidal_check = toroidal_check.clone();
+            let opponent = Rc::clone(&opponent);
+            config_button.set_callback(move |_| {
+                let Some(path) = dialog::file_chooser("Experiment config", "*.json", ".", false)
+                else {
+                    return;
+                };
+                match ExperimentConfig::load(Path::new(&path)) {
+                    Ok(config) => {
+                        rows_input.set_value(&config.environment.rows.to_string());
+                        columns_input.set_value(&config.environment.columns.to_string());
+                        win_condition_input
+                            .set_value(&config.environment.win_condition.to_string());
+                        misere_check.set_checked(config.environment.variant == Variant::Misere);
+                        gravity_check.set_checked(config.environment.gravity);
+                        toroidal_check.set_checked(config.environment.toroidal);
+                        position_input.set_value(config.start.as_deref().unwrap_or_default());
+                        *opponent.borrow_mut() = Some(config.agent_spec());
+                        println!("Loaded experiment config {}", path);
+                    }
+                    Err(err) => dialog::alert_default(&format!("{:#}", err)),
+                }
+            });
+        }
 
-        let board_size_clone = board_size_input.clone();
+        let rows_clone = rows_input.clone();
+        let columns_clone = columns_input.clone();
         let win_condition_clone = win_condition_input.clone();
+        let position_clone = position_input.clone();
         let wind_cloned = Rc::clone(&wind);
 
         let button_size = 45;
 
         submit_button.set_callback(move |_| {
-            let board_size: usize = board_size_clone.value().parse().unwrap_or(3);
-            let win_condition: usize = win_condition_clone.value().parse().unwrap_or(3);
+            let (Some(rows), Some(columns), Some(win_condition)) = (
+                parse_input(&rows_clone, "Rows"),
+                parse_input(&columns_clone, "Columns"),
+                parse_input(&win_condition_clone, "Win condition"),
+            ) else {
+                return;
+            };
+            let config = BoardConfig {
+                rows,
+                columns,
+                win_condition,
+                variant: if misere_check.is_checked() {
+                    Variant::Misere
+                } else {
+                    Variant::Standard
+                },
+                gravity: gravity_check.is_checked(),
+                toroidal: toroidal_check.is_checked(),
+            };
 
             // Game stage
-            let mut _board = Board::new(board_size, win_condition);
+            let position = position_clone.value();
+            let position = (!position.trim().is_empty()).then(|| position.trim().to_string());
+            let _board = Board::from_start(position.as_deref(), config);
+            let _board = match _board {
+                Ok(board) => board,
+                Err(err) => {
+                    dialog::alert_default(&format!("{:#}", err));
+                    return;
+                }
+            };
+            // A start position sets the dimensions
+            let config = _board.config();
+            let (rows, columns) = (config.rows, config.columns);
             let _board = Rc::new(RefCell::new(_board));
-            let _board_cloned = _board.clone();
-
-            // Train agent
-            let mut agent: Option<Rc<RefCell<QTable>>> = None;
-            if train_agent {
-                use std::time::Instant;
-                let start = Instant::now();
-                agent = Some(Rc::new(RefCell::new(train(
-                    1000,
-                    200,
-                    board_size,
-                    win_condition,
-                ))));
-                let duration = start.elapsed();
-                println!("Trained agent in: {:?}", duration);
-            }
-            let game_window_size = board_size as i32 * button_size;
 
+            // Create the agent playing O, if any
+            let agent: Option<Rc<RefCell<Box<dyn Agent>>>> = match opponent.borrow().as_ref() {
+                Some(spec) => {
+                    use std::time::Instant;
+                    let start = Instant::now();
+                    // Learners train from the start position of the game
+                    let spec = spec.clone().with_start(position.clone());
+                    match spec.build(config, &mut rand::thread_rng()) {
+                        Ok(agent) => {
+                            let duration = start.elapsed();
+                            println!("Built agent in: {:?}", duration);
+                            Some(Rc::new(RefCell::new(agent)))
+                        }
+                        Err(err) => {
+                            eprintln!("Cannot build the agent, error {:#}", err);
+                            return;
+                        }
+                    }
+                }
+                None => None,
+            };
             let game_wind = Rc::new(RefCell::new(Window::new(
                 100,
                 100,
-                game_window_size,
-                game_window_size,
+                columns as i32 * button_size,
+                rows as i32 * button_size,
                 "Tic Tac Toe",
             )));
             game_wind.borrow_mut().make_resizable(true);
@@ -92,12 +192,14 @@ impl TicTacToeApp {
                 game_wind.borrow_mut().draw(move |_: &mut Window| {
                     let wind = game_wind_ref.borrow_mut();
                     draw::set_draw_color(enums::Color::DarkGreen);
-                    let sz_x = wind.width() / board_size as i32;
-                    let sz_y = wind.height() / board_size as i32;
+                    let sz_x = wind.width() / columns as i32;
+                    let sz_y = wind.height() / rows as i32;
 
-                    for i in 1..board_size {
+                    for i in 1..columns {
                         draw::draw_line(i as i32 * sz_x, 0, i as i32 * sz_x, wind.height());
-                        draw::draw_line(0, i as i32 * sz_y, wind.width(), i as i32 * sz_y);
+                    }
+                    for j in 1..rows {
+                        draw::draw_line(0, j as i32 * sz_y, wind.width(), j as i32 * sz_y);
                     }
                 });
 
@@ -113,130 +215,94 @@ impl TicTacToeApp {
             }
 
             // Capture events on buttons
-            for i in 0..board_size {
-                for j in 0..board_size {
-                    let mut cell = Button::new(
-                        i as i32 * button_size,
-                        j as i32 * button_size,
-                        button_size,
-                        button_size,
-                        "",
-                    );
-                    cell.set_frame(enums::FrameType::BorderFrame);
-
-                    let cell = Rc::new(RefCell::new(cell));
-                    let cell_cloned = cell.clone();
+            let cells: Vec<Vec<Button>> = (0..columns)
+                .map(|i| {
+                    (0..rows)
+                        .map(|j| {
+                            let mut cell = Button::new(
+                                i as i32 * button_size,
+                                j as i32 * button_size,
+                                button_size,
+                                button_size,
+                                "",
+                            );
+                            // Filled so the cells can be shaded by the agent's Q-values
+                            cell.set_frame(enums::FrameType::BorderBox);
+                            cell.set_color(enums::Color::BackGround);
+                            // Marks and obstacles of the start position
+                            cell.set_label(symbol_label(_board.borrow().get_grid()[i][j]));
+                            cell
+                        })
+                        .collect()
+                })
+                .collect();
+            let cells = Rc::new(cells);
+
+            for i in 0..columns {
+                for j in 0..rows {
                     let board = _board.clone();
+                    let cells_cloned = cells.clone();
                     let game_wind_cloned = game_wind.clone();
                     let agent_cloned = agent.as_ref().cloned();
 
                     // Callback closure
-                    cell.borrow_mut().set_callback(move |_| {
+                    cells[i][j].clone().set_callback(move |_| {
                         println!("Attempting to play move [{}, {}]", i, j);
-                        if board.borrow_mut().is_valid_move(i, j) {
-                            let player = board.borrow_mut().get_current_player();
-                            match player {
-                                CellState::X => {
-                                    cell_cloned.borrow_mut().set_label("X");
-                                    match &agent_cloned {
-                                        Some(agent_) => {
-                                            let possible_actions = board
-                                                .borrow_mut()
-                                                .get_possible_actions()
-                                                .iter()
-                                                .map(|&(x_axis, y_axis)| Action { x_axis, y_axis })
-                                                .collect::<Vec<Action>>();
-                                            let action = agent_.borrow_mut().epsilon_greedy_search(
-                                                &board.borrow_mut().get_grid(),
-                                                &possible_actions,
-                                            );
-                                            println!("Agent play: {:?}", action);
-                                        }
-                                        None => {}
-                                    }
-                                }
-                                CellState::O => {
-                                    cell_cloned.borrow_mut().set_label("O");
-                                }
-                                CellState::Empty => {}
-                            };
-                            board.borrow_mut().play_move(i, j);
-                            println!("Played move [{}, {}]", i, j);
-
-                            // End conditions
-                            let mut outcome = Outcomes::Undefined;
-
-                            // Win condition
-                            if let Some(winner) = board.borrow_mut().is_winner() {
-                                println!("Winner: {:?}", winner);
-                                outcome = match winner {
-                                    CellState::Empty => Outcomes::Undefined,
-                                    CellState::X => Outcomes::Win,
-                                    CellState::O => Outcomes::Loss,
+                        if !board.borrow().is_valid_move(i, j) {
+                            return;
+                        }
+                        clear_shading(&cells_cloned);
+                        play_and_label(&mut board.borrow_mut(), &cells_cloned, i, j);
+                        println!("Played move [{}, {}]", i, j);
+
+                        let mut outcome = get_outcome(&*board.borrow());
+                        if outcome == Outcomes::Undefined {
+                            if let Some(agent_) = &agent_cloned {
+                                if let Some(values) = agent_.borrow().action_values(&board.borrow())
+                                {
+                                    shade_cells(&cells_cloned, &values);
                                 }
-                            }
-
-                            // Board full condition
-                            if board.borrow_mut().is_board_full() && outcome == Outcomes::Undefined
-                            {
-                                println!("Board full");
-                                outcome = Outcomes::Draw;
-                            }
-
-                            if outcome != Outcomes::Undefined {
-                                game_wind_cloned.borrow_mut().hide();
-                                // Create a new window
-                                let result_wind = Rc::new(RefCell::new(Window::new(
-                                    200,
-                                    200,
-                                    1024,
-                                    1024,
-                                    "Game Ended",
-                                )));
-                                let mut frame = Frame::new(0, 0, 1024, 1024, "");
-
-                                let image_bytes = match outcome {
-                                    Outcomes::Win => WIN_IMAGE,
-                                    Outcomes::Draw => DRAW_IMAGE,
-                                    Outcomes::Loss => LOSS_IMAGE,
-                                    Outcomes::Undefined => EMPTY_IMAGE,
-                                };
-
-                                let image = match PngImage::from_data(image_bytes) {
-                                    Ok(img) => img,
-                                    Err(err) => {
-                                        eprintln!("Cannot load image, error {}", err);
-                                        return;
-                                    }
-                                };
-
-                                frame.set_image(Some(image));
-
-                                /*
-                                let mut play_again_button = Button::new(
-                                    1024 / 2,
-                                    1024 / 4 - 200 / 2,
-                                    200 / 2,
-                                    200 / 2,
-                                    "Play again?",
+                                let action = agent_
+                                    .borrow_mut()
+                                    .select_action(&board.borrow(), &mut rand::thread_rng());
+                                println!("Agent play: {:?}", action);
+                                play_and_label(
+                                    &mut board.borrow_mut(),
+                                    &cells_cloned,
+                                    action.x_axis,
+                                    action.y_axis,
                                 );
-
-                                let result_wind_closed = result_wind.clone();
-                                let board_cloned = board.clone();
-                                play_again_button.set_callback(move |_| {
-                                    result_wind_closed.borrow_mut().hide();
-                                    board_cloned.borrow_mut().reset();
-                                    // TODO go back to initial screen
-                                });
-                                */
-                                result_wind.borrow_mut().end();
-                                result_wind.borrow_mut().show();
+                                outcome = get_outcome(&*board.borrow());
                             }
                         }
+
+                        if outcome != Outcomes::Undefined {
+                            game_wind_cloned.borrow_mut().hide();
+                            show_outcome(outcome);
+                        }
                     });
                 }
             }
 
+            // The agent opens when the start position gives it the move
+            if let Some(agent_) = &agent {
+                if _board.borrow().get_current_player() == CellState::O {
+                    if let Some(values) = agent_.borrow().action_values(&_board.borrow()) {
+                        shade_cells(&cells, &values);
+                    }
+                    let action = agent_
+                        .borrow_mut()
+                        .select_action(&_board.borrow(), &mut rand::thread_rng());
+                    println!("Agent play: {:?}", action);
+                    play_and_label(
+                        &mut _board.borrow_mut(),
+                        &cells,
+                        action.x_axis,
+                        action.y_axis,
+                    );
+                }
+            }
+
             wind_cloned.borrow_mut().hide();
             game_wind.borrow_mut().end();
             game_wind.borrow_mut().show();
@@ -247,4 +313,555 @@ impl TicTacToeApp {
 
         app.run().unwrap();
     }
+
+    /// Opens an ultimate tic-tac-toe game, the cells where the player to move can play are
+    /// highlighted and won sub-boards take the colour of their winner. The table, if any, plays O.
+    pub fn run_ultimate(opponent: Option<QTable>) {
+        let app = app::App::default();
+        let n_cells = (ultimate::SIZE * ultimate::SIZE) as i32;
+        let button_size = 45;
+        // Sub-boards are separated by a gap
+        let gap = 6;
+        let offset =
+            |index: usize| index as i32 * button_size + (index / ultimate::SIZE) as i32 * gap;
+        let side = n_cells * button_size + (ultimate::SIZE as i32 - 1) * gap;
+        let wind = Window::new(100, 100, side, side, "Ultimate Tic Tac Toe");
+
+        let cells: Vec<Vec<Button>> = (0..n_cells as usize)
+            .map(|i| {
+                (0..n_cells as usize)
+                    .map(|j| {
+                        let mut cell =
+                            Button::new(offset(i), offset(j), button_size, button_size, "");
+                        cell.set_frame(enums::FrameType::BorderBox);
+                        cell
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells = Rc::new(cells);
+        let board = Rc::new(RefCell::new(UltimateBoard::new()));
+        let opponent = Rc::new(opponent);
+        highlight_sub_boards(&board.borrow(), &cells);
+
+        for i in 0..n_cells as usize {
+            for j in 0..n_cells as usize {
+                let board = board.clone();
+                let cells_cloned = cells.clone();
+                let opponent = opponent.clone();
+                let mut wind = wind.clone();
+                cells[i][j].clone().set_callback(move |_| {
+                    if !board.borrow().is_valid_move(i, j) {
+                        return;
+                    }
+                    play_ultimate_and_label(&mut board.borrow_mut(), &cells_cloned, i, j);
+                    let mut outcome = get_outcome(&*board.borrow());
+                    if outcome == Outcomes::Undefined {
+                        if let Some(table) = opponent.as_ref() {
+                            let action = ultimate::select_action(
+                                table,
+                                &board.borrow(),
+                                &mut rand::thread_rng(),
+                            );
+                            println!("Agent play: {:?}", action);
+                            play_ultimate_and_label(
+                                &mut board.borrow_mut(),
+                                &cells_cloned,
+                                action.x_axis,
+                                action.y_axis,
+                            );
+                            outcome = get_outcome(&*board.borrow());
+                        }
+                    }
+                    highlight_sub_boards(&board.borrow(), &cells_cloned);
+
+                    if outcome != Outcomes::Undefined {
+                        wind.hide();
+                        show_outcome(outcome);
+                    }
+                });
+            }
+        }
+
+        wind.end();
+        wind.clone().show();
+
+        app.run().unwrap();
+    }
+
+    /// Opens a wild game, the symbol placed by a click is chosen above the board. The table, if
+    /// any, plays O and picks its own symbols.
+    pub fn run_wild(wild: WildBoard, opponent: Option<QTable>) {
+        let app = app::App::default();
+        let config = wild.config();
+        let button_size = 45;
+        // Height of the symbol choice above the board
+        let header = 40;
+        let wind = Window::new(
+            100,
+            100,
+            (config.columns as i32 * button_size).max(200),
+            config.rows as i32 * button_size + header,
+            "Wild Tic Tac Toe",
+        );
+        let mut place_x = RadioRoundButton::new(10, 10, 90, 20, "Place X");
+        place_x.set_value(true);
+        RadioRoundButton::new(100, 10, 90, 20, "Place O");
+
+        let cells: Vec<Vec<Button>> = (0..config.columns)
+            .map(|i| {
+                (0..config.rows)
+                    .map(|j| {
+                        Button::new(
+                            i as i32 * button_size,
+                            header + j as i32 * button_size,
+                            button_size,
+                            button_size,
+                            "",
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells = Rc::new(cells);
+        let wild = Rc::new(RefCell::new(wild));
+        let opponent = Rc::new(opponent);
+
+        for i in 0..config.columns {
+            for j in 0..config.rows {
+                let wild = wild.clone();
+                let cells_cloned = cells.clone();
+                let opponent = opponent.clone();
+                let place_x = place_x.clone();
+                let mut wind = wind.clone();
+                cells[i][j].clone().set_callback(move |_| {
+                    if !wild.borrow().is_valid_move(i, j) {
+                        return;
+                    }
+                    let symbol = if place_x.value() {
+                        CellState::X
+                    } else {
+                        CellState::O
+                    };
+                    play_wild_and_label(&mut wild.borrow_mut(), &cells_cloned, i, j, symbol);
+                    let mut outcome = get_outcome(&*wild.borrow());
+                    if outcome == Outcomes::Undefined {
+                        if let Some(table) = opponent.as_ref() {
+                            let action =
+                                table.greedy_action(&*wild.borrow(), &mut rand::thread_rng());
+                            println!("Agent play: {:?}", action);
+                            play_wild_and_label(
+                                &mut wild.borrow_mut(),
+                                &cells_cloned,
+                                action.x_axis,
+                                action.y_axis,
+                                action.symbol.unwrap_or(CellState::O),
+                            );
+                            outcome = get_outcome(&*wild.borrow());
+                        }
+                    }
+
+                    if outcome != Outcomes::Undefined {
+                        wind.hide();
+                        show_outcome(outcome);
+                    }
+                });
+            }
+        }
+
+        wind.end();
+        wind.clone().show();
+
+        app.run().unwrap();
+    }
+
+    /// Opens a Notakto game with the boards side by side, dead boards are greyed out. The table,
+    /// if any, plays second.
+    pub fn run_notakto(notakto: Notakto, opponent: Option<QTable>) {
+        let app = app::App::default();
+        let config = notakto.config();
+        let n_boards = notakto.n_boards();
+        let button_size = 45;
+        // Boards are separated by a gap
+        let gap = 20;
+        let board_width = config.columns as i32 * button_size + gap;
+        let wind = Window::new(
+            100,
+            100,
+            n_boards as i32 * board_width - gap,
+            config.rows as i32 * button_size,
+            "Notakto",
+        );
+
+        // Indexed by board, then x then y
+        let cells: Vec<Vec<Vec<Button>>> = (0..n_boards)
+            .map(|index| {
+                (0..config.columns)
+                    .map(|i| {
+                        (0..config.rows)
+                            .map(|j| {
+                                let mut cell = Button::new(
+                                    index as i32 * board_width + i as i32 * button_size,
+                                    j as i32 * button_size,
+                                    button_size,
+                                    button_size,
+                                    "",
+                                );
+                                cell.set_frame(enums::FrameType::BorderBox);
+                                cell.set_color(enums::Color::BackGround);
+                                cell
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells = Rc::new(cells);
+        let notakto = Rc::new(RefCell::new(notakto));
+        let opponent = Rc::new(opponent);
+
+        for index in 0..n_boards {
+            for i in 0..config.columns {
+                for j in 0..config.rows {
+                    let notakto = notakto.clone();
+                    let cells_cloned = cells.clone();
+                    let opponent = opponent.clone();
+                    let mut wind = wind.clone();
+                    cells[index][i][j].clone().set_callback(move |_| {
+                        if !notakto.borrow().is_valid_move(i, j, index) {
+                            return;
+                        }
+                        play_notakto_and_label(
+                            &mut notakto.borrow_mut(),
+                            &cells_cloned,
+                            i,
+                            j,
+                            index,
+                        );
+                        let mut outcome = get_outcome(&*notakto.borrow());
+                        if outcome == Outcomes::Undefined {
+                            if let Some(table) = opponent.as_ref() {
+                                let action = table
+                                    .greedy_action(&*notakto.borrow(), &mut rand::thread_rng());
+                                println!("Agent play: {:?}", action);
+                                play_notakto_and_label(
+                                    &mut notakto.borrow_mut(),
+                                    &cells_cloned,
+                                    action.x_axis,
+                                    action.y_axis,
+                                    action.layer,
+                                );
+                                outcome = get_outcome(&*notakto.borrow());
+                            }
+                        }
+
+                        if outcome != Outcomes::Undefined {
+                            wind.hide();
+                            show_outcome(outcome);
+                        }
+                    });
+                }
+            }
+        }
+
+        wind.end();
+        wind.clone().show();
+
+        app.run().unwrap();
+    }
+
+    /// Opens a 3D game with the layers of the cube side by side, from the first on the left.
+    /// The table, if any, plays O.
+    pub fn run_cube(cube: Cube, opponent: Option<QTable>) {
+        let app = app::App::default();
+        let size = cube.size();
+        let button_size = 45;
+        // Layers are separated by a gap
+        let gap = 20;
+        let layer_width = size as i32 * button_size + gap;
+        let wind = Window::new(
+            100,
+            100,
+            size as i32 * layer_width - gap,
+            size as i32 * button_size,
+            "3D Tic Tac Toe",
+        );
+
+        // Indexed by layer, then x then y
+        let cells: Vec<Vec<Vec<Button>>> = (0..size)
+            .map(|layer| {
+                (0..size)
+                    .map(|i| {
+                        (0..size)
+                            .map(|j| {
+                                Button::new(
+                                    layer as i32 * layer_width + i as i32 * button_size,
+                                    j as i32 * button_size,
+                                    button_size,
+                                    button_size,
+                                    "",
+                                )
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells = Rc::new(cells);
+        let cube = Rc::new(RefCell::new(cube));
+        let opponent = Rc::new(opponent);
+
+        for layer in 0..size {
+            for i in 0..size {
+                for j in 0..size {
+                    let cube = cube.clone();
+                    let cells_cloned = cells.clone();
+                    let opponent = opponent.clone();
+                    let mut wind = wind.clone();
+                    cells[layer][i][j].clone().set_callback(move |_| {
+                        if !cube.borrow().is_valid_move(i, j, layer) {
+                            return;
+                        }
+                        play_cube_and_label(
+                            &mut cube.borrow_mut(),
+                            &cells_cloned,
+                            Action {
+                                x_axis: i,
+                                y_axis: j,
+                                layer,
+                                symbol: None,
+                            },
+                        );
+                        let mut outcome = get_outcome(&*cube.borrow());
+                        if outcome == Outcomes::Undefined {
+                            if let Some(table) = opponent.as_ref() {
+                                let action =
+                                    table.greedy_action(&*cube.borrow(), &mut rand::thread_rng());
+                                println!("Agent play: {:?}", action);
+                                play_cube_and_label(&mut cube.borrow_mut(), &cells_cloned, action);
+                                outcome = get_outcome(&*cube.borrow());
+                            }
+                        }
+
+                        if outcome != Outcomes::Undefined {
+                            wind.hide();
+                            show_outcome(outcome);
+                        }
+                    });
+                }
+            }
+        }
+
+        wind.end();
+        wind.clone().show();
+
+        app.run().unwrap();
+    }
+}
+
+/// Reads a number from a setup input, an alert tells what's wrong with invalid ones
+fn parse_input(input: &Input, name: &str) -> Option<usize> {
+    match input.value().trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            dialog::alert_default(&format!(
+                "{} must be a whole number, not `{}`",
+                name,
+                input.value()
+            ));
+            None
+        }
+    }
+}
+
+/// Text shown on a cell holding the symbol
+fn symbol_label(cell: CellState) -> &'static str {
+    match cell {
+        CellState::X => "X",
+        CellState::O => "O",
+        CellState::Blocked => "#",
+        CellState::Empty => "",
+    }
+}
+
+/// Plays the move for the current player and writes its symbol on the cell the mark lands on,
+/// with gravity a click anywhere in a column drops the mark down that column
+fn play_and_label(board: &mut Board, cells: &[Vec<Button>], x_axis: usize, y_axis: usize) {
+    let (x_axis, y_axis) = board.landing_cell(x_axis, y_axis);
+    let label = symbol_label(board.get_current_player());
+    cells[x_axis][y_axis].clone().set_label(label);
+    board.play_move(x_axis, y_axis);
+}
+
+/// Plays the ultimate move for the current player and writes its symbol on the matching cell
+fn play_ultimate_and_label(
+    board: &mut UltimateBoard,
+    cells: &[Vec<Button>],
+    x_axis: usize,
+    y_axis: usize,
+) {
+    let label = symbol_label(board.get_current_player());
+    cells[x_axis][y_axis].clone().set_label(label);
+    board.play_move(x_axis, y_axis);
+}
+
+/// Places the chosen symbol for the current player and writes it on the cell it lands on
+fn play_wild_and_label(
+    wild: &mut WildBoard,
+    cells: &[Vec<Button>],
+    x_axis: usize,
+    y_axis: usize,
+    symbol: CellState,
+) {
+    let (x_axis, y_axis) = wild.landing_cell(x_axis, y_axis);
+    let label = symbol_label(symbol);
+    cells[x_axis][y_axis].clone().set_label(label);
+    wild.play_move(x_axis, y_axis, symbol);
+}
+
+/// Places an X on the board, which is greyed out once dead
+fn play_notakto_and_label(
+    notakto: &mut Notakto,
+    cells: &[Vec<Vec<Button>>],
+    x_axis: usize,
+    y_axis: usize,
+    index: usize,
+) {
+    cells[index][x_axis][y_axis].clone().set_label("X");
+    notakto.play_move(x_axis, y_axis, index);
+    if notakto.is_dead(index) {
+        for cell in cells[index].iter().flatten() {
+            let mut cell = cell.clone();
+            cell.set_color(enums::Color::Dark2);
+            cell.redraw();
+        }
+    }
+}
+
+/// Plays the 3D move for the current player and writes its symbol on the matching cell
+fn play_cube_and_label(cube: &mut Cube, cells: &[Vec<Vec<Button>>], action: Action) {
+    let label = symbol_label(cube.get_current_player());
+    cells[action.layer][action.x_axis][action.y_axis]
+        .clone()
+        .set_label(label);
+    cube.play(action);
+}
+
+/// Colours the sub-board the player to move has to play in, or every open cell when free to
+/// play anywhere, and the sub-boards already won
+fn highlight_sub_boards(board: &UltimateBoard, cells: &[Vec<Button>]) {
+    for (i, column) in cells.iter().enumerate() {
+        for (j, cell) in column.iter().enumerate() {
+            let sub_board = (i / ultimate::SIZE, j / ultimate::SIZE);
+            let highlighted = match board.active_board() {
+                Some(active) => active == sub_board,
+                None => board.is_valid_move(i, j),
+            };
+            let color = match board.sub_board_winner(sub_board.0, sub_board.1) {
+                Some(CellState::X) => enums::Color::from_rgb(190, 210, 255),
+                Some(CellState::O) => enums::Color::from_rgb(255, 200, 200),
+                _ if highlighted => enums::Color::from_rgb(255, 250, 190),
+                _ => enums::Color::BackGround,
+            };
+            let mut cell = cell.clone();
+            cell.set_color(color);
+            cell.redraw();
+        }
+    }
+}
+
+/// Shades the empty cells by the value the agent gave to playing there, from red for its worst
+/// move to green for its best, the value itself is shown as tooltip
+fn shade_cells(cells: &[Vec<Button>], values: &[(Action, f64)]) {
+    let min = values.iter().map(|&(_, q)| q).fold(f64::INFINITY, f64::min);
+    let max = values
+        .iter()
+        .map(|&(_, q)| q)
+        .fold(f64::NEG_INFINITY, f64::max);
+    for &(action, q) in values {
+        let (red, green, blue) = shade(q, min, max);
+        let mut cell = cells[action.x_axis][action.y_axis].clone();
+        cell.set_color(enums::Color::from_rgb(red, green, blue));
+        cell.set_tooltip(&format!("Q {:.3}", q));
+        cell.redraw();
+    }
+}
+
+fn clear_shading(cells: &[Vec<Button>]) {
+    for cell in cells.iter().flatten() {
+        let mut cell = cell.clone();
+        cell.set_color(enums::Color::BackGround);
+        cell.set_tooltip("");
+        cell.redraw();
+    }
+}
+
+/// Returns the outcome of the game from the point of view of X
+fn get_outcome(board: &impl Game) -> Outcomes {
+    // Win condition
+    if let Some(winner) = board.winner() {
+        println!("Winner: {:?}", winner);
+        return match winner {
+            CellState::Empty | CellState::Blocked => Outcomes::Undefined,
+            CellState::X => Outcomes::Win,
+            CellState::O => Outcomes::Loss,
+        };
+    }
+
+    // Board full condition
+    if board.is_full() {
+        println!("Board full");
+        return Outcomes::Draw;
+    }
+    Outcomes::Undefined
+}
+
+/// Opens the window displaying the end of game image
+fn show_outcome(outcome: Outcomes) {
+    // Create a new window
+    let result_wind = Rc::new(RefCell::new(Window::new(
+        200,
+        200,
+        1024,
+        1024,
+        "Game Ended",
+    )));
+    let mut frame = Frame::new(0, 0, 1024, 1024, "");
+
+    let image_bytes = match outcome {
+        Outcomes::Win => WIN_IMAGE,
+        Outcomes::Draw => DRAW_IMAGE,
+        Outcomes::Loss => LOSS_IMAGE,
+        Outcomes::Undefined => EMPTY_IMAGE,
+    };
+
+    let image = match PngImage::from_data(image_bytes) {
+        Ok(img) => img,
+        Err(err) => {
+            eprintln!("Cannot load image, error {}", err);
+            return;
+        }
+    };
+
+    frame.set_image(Some(image));
+
+    /*
+    let mut play_again_button = Button::new(
+        1024 / 2,
+        1024 / 4 - 200 / 2,
+        200 / 2,
+        200 / 2,
+        "Play again?",
+    );
+
+    let result_wind_closed = result_wind.clone();
+    let board_cloned = board.clone();
+    play_again_button.set_callback(move |_| {
+        result_wind_closed.borrow_mut().hide();
+        board_cloned.borrow_mut().reset();
+        // TODO go back to initial screen
+    });
+    */
+    result_wind.borrow_mut().end();
+    result_wind.borrow_mut().show();
 }
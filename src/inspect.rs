@@ -0,0 +1,113 @@
+use std::fmt::Write;
+
+use crate::{
+    agent::Action,
+    logic::{Board, CellState},
+};
+
+/// What a table knows about a legal move
+#[derive(Clone, Copy, Debug)]
+pub struct CellStats {
+    pub action: Action,
+    pub q: f64,
+    pub visits: usize,
+}
+
+/// Colour of a value between `min` and `max`, from red for the lowest to green for the highest
+pub fn shade(value: f64, min: f64, max: f64) -> (u8, u8, u8) {
+    let fraction = if max > min {
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    let red = 220.0 - 150.0 * fraction;
+    let green = 70.0 + 150.0 * fraction;
+    (red as u8, green as u8, 70)
+}
+
+/// Draws the board with the Q-value and visit count of every legal move, the greedy moves are
+/// starred. With `color` set, legal cells get an ANSI background shaded by their value.
+pub fn heatmap(board: &Board, stats: &[CellStats], color: bool) -> String {
+    const WIDTH: usize = 16;
+    let grid = board.get_grid();
+    let columns = grid.len();
+    let min = stats.iter().map(|s| s.q).fold(f64::INFINITY, f64::min);
+    let max = stats.iter().map(|s| s.q).fold(f64::NEG_INFINITY, f64::max);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{} to play on {}",
+        match board.get_current_player() {
+            CellState::X => "X",
+            _ => "O",
+        },
+        board.to_position()
+    )
+    .unwrap();
+    let separator = format!("+{}\n", format!("{}+", "-".repeat(WIDTH)).repeat(columns));
+    out.push_str(&separator);
+    for y_axis in 0..grid[0].len() {
+        out.push('|');
+        for (x_axis, column) in grid.iter().enumerate() {
+            let cell_stats = stats
+                .iter()
+                .find(|s| s.action.x_axis == x_axis && s.action.y_axis == y_axis);
+            let text = match (column[y_axis], cell_stats) {
+                (CellState::X, _) => "X".to_string(),
+                (CellState::O, _) => "O".to_string(),
+                (CellState::Blocked, _) => "#".to_string(),
+                (CellState::Empty, Some(s)) => format!(
+                    "{}{:+.3} ({})",
+                    if s.q == max { "*" } else { "" },
+                    s.q,
+                    s.visits
+                ),
+                (CellState::Empty, None) => String::new(),
+            };
+            match cell_stats {
+                Some(s) if color => {
+                    let (red, green, blue) = shade(s.q, min, max);
+                    write!(
+                        out,
+                        "\x1b[48;2;{};{};{}m{:^WIDTH$}\x1b[0m|",
+                        red, green, blue, text
+                    )
+                    .unwrap();
+                }
+                _ => write!(out, "{:^WIDTH$}|", text).unwrap(),
+            }
+        }
+        out.push('\n');
+        out.push_str(&separator);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::BoardConfig;
+
+    #[test]
+    fn test_heatmap_stars_greedy_moves() {
+        let board = Board::from_position("XO./.../...", BoardConfig::default()).unwrap();
+        let stats: Vec<CellStats> = Action::possible_actions(&board)
+            .into_iter()
+            .map(|action| CellStats {
+                action,
+                q: if (action.x_axis, action.y_axis) == (1, 1) {
+                    0.75
+                } else {
+                    -0.25
+                },
+                visits: 3,
+            })
+            .collect();
+        let map = heatmap(&board, &stats, false);
+        assert_eq!(map.matches('*').count(), 1);
+        assert!(map.contains("*+0.750 (3)"));
+        assert!(map.contains("-0.250 (3)"));
+        assert_eq!(map.lines().count(), 8);
+    }
+}
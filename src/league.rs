@@ -0,0 +1,252 @@
+use anyhow::{anyhow, bail, ensure, Context};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{
+    agent::{Agent, HeuristicAgent, QTable, RandomAgent},
+    minimax::MinimaxAgent,
+    policy::Policy,
+};
+
+/// Kind of opponent the learner can be matched against
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OpponentKind {
+    Random,
+    Heuristic,
+    Minimax {
+        depth: usize,
+    },
+    /// Frozen copy of the learner from an earlier epoch
+    Snapshot,
+}
+
+/// Mix of opponents of a training run, opponents are drawn for every game proportionally to
+/// their weight
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "LeagueFields")]
+pub struct League {
+    pub opponents: Vec<(OpponentKind, f64)>,
+    /// Number of epochs between two snapshots of the learner
+    pub snapshot_every: usize,
+    /// Number of snapshots kept, the oldest are dropped first
+    pub max_snapshots: usize,
+}
+
+/// League as written in configs and checkpoints, checked before it can be sampled from
+#[derive(Deserialize)]
+struct LeagueFields {
+    opponents: Vec<(OpponentKind, f64)>,
+    snapshot_every: usize,
+    max_snapshots: usize,
+}
+
+impl TryFrom<LeagueFields> for League {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: LeagueFields) -> anyhow::Result<Self> {
+        let league = Self {
+            opponents: fields.opponents,
+            snapshot_every: fields.snapshot_every,
+            max_snapshots: fields.max_snapshots,
+        };
+        league.validate()?;
+        Ok(league)
+    }
+}
+
+impl League {
+    /// Checks that an opponent can be drawn, and that a snapshot is kept when one can be drawn
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(!self.opponents.is_empty(), "The league has no opponent");
+        ensure!(
+            self.opponents.iter().all(|&(_, weight)| weight >= 0.0),
+            "Opponent weights can't be negative"
+        );
+        ensure!(
+            self.opponents.iter().any(|&(_, weight)| weight > 0.0),
+            "At least one opponent needs a positive weight"
+        );
+        ensure!(
+            self.max_snapshots > 0
+                || self
+                    .opponents
+                    .iter()
+                    .all(|&(kind, _)| kind != OpponentKind::Snapshot),
+            "A league with snapshots has to keep at least one"
+        );
+        Ok(())
+    }
+}
+
+impl OpponentKind {
+    /// Builds the opponent, snapshots are built from the learner by the pool instead
+    pub fn build(self) -> Option<Box<dyn Agent>> {
+        match self {
+            OpponentKind::Random => Some(Box::new(RandomAgent)),
+            OpponentKind::Heuristic => Some(Box::new(HeuristicAgent)),
+            OpponentKind::Minimax { depth } => Some(Box::new(MinimaxAgent::new(depth))),
+            OpponentKind::Snapshot => None,
+        }
+    }
+}
+
+impl FromStr for OpponentKind {
+    type Err = anyhow::Error;
+
+    /// Parses `random`, `heuristic`, `minimax:<depth>` or `snapshot`
+    fn from_str(kind: &str) -> anyhow::Result<Self> {
+        Ok(match kind.split_once(':') {
+            None if kind == "random" => OpponentKind::Random,
+            None if kind == "heuristic" => OpponentKind::Heuristic,
+            None if kind == "snapshot" => OpponentKind::Snapshot,
+            Some(("minimax", depth)) => OpponentKind::Minimax {
+                depth: depth
+                    .parse()
+                    .with_context(|| format!("Invalid depth in opponent `{}`", kind))?,
+            },
+            _ => bail!("Unknown opponent `{}`", kind),
+        })
+    }
+}
+
+impl FromStr for League {
+    type Err = anyhow::Error;
+
+    /// Parses a comma separated list of `kind=weight` where the kind is `random`, `heuristic`,
+    /// `minimax:<depth>` or `snapshot`, e.g. `random=1,minimax:2=1,snapshot=2`
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let opponents = spec
+            .split(',')
+            .map(|entry| {
+                let (kind, weight) = entry
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Missing weight in opponent `{}`", entry))?;
+                let weight: f64 = weight
+                    .parse()
+                    .with_context(|| format!("Invalid weight in opponent `{}`", entry))?;
+                if weight < 0.0 {
+                    bail!("Negative weight in opponent `{}`", entry);
+                }
+                Ok((kind.parse()?, weight))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let league = Self {
+            opponents,
+            snapshot_every: 10,
+            max_snapshots: 5,
+        };
+        league.validate()?;
+        Ok(league)
+    }
+}
+
+/// Opponents of a training run, with the snapshots of the learner taken so far
+#[derive(Serialize, Deserialize)]
+pub struct LeaguePool {
+    league: League,
+    /// Fixed opponents, built on first use so a pool read from a checkpoint rebuilds them
+    #[serde(skip)]
+    agents: Vec<Option<Box<dyn Agent>>>,
+    snapshots: Vec<QTable>,
+}
+
+impl LeaguePool {
+    pub fn new(league: &League) -> Self {
+        Self {
+            league: league.clone(),
+            agents: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    fn build_agents(&mut self) {
+        self.agents = self
+            .league
+            .opponents
+            .iter()
+            .map(|&(kind, _)| kind.build())
+            .collect();
+    }
+
+    /// Freezes a greedy copy of the learner if a snapshot is due at this epoch
+    pub fn maybe_snapshot(&mut self, learner: &QTable, epoch: usize) {
+        if epoch.is_multiple_of(self.league.snapshot_every.max(1)) {
+            let mut snapshot = learner.clone();
+            snapshot.set_policy(Policy::Greedy);
+            self.snapshots.push(snapshot);
+            if self.snapshots.len() > self.league.max_snapshots {
+                self.snapshots.remove(0);
+            }
+        }
+    }
+
+    /// Draws the index of the opponent of the next game
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let weights: Vec<f64> = self.league.opponents.iter().map(|&(_, w)| w).collect();
+        let distribution = rand::distributions::WeightedIndex::new(&weights)
+            .expect("Leagues are checked to have a positive weight");
+        distribution.sample(rng)
+    }
+
+    pub fn kind(&self, index: usize) -> OpponentKind {
+        self.league.opponents[index].0
+    }
+
+    pub fn n_opponents(&self) -> usize {
+        self.league.opponents.len()
+    }
+
+    /// Returns the opponent with the given index, snapshots are picked at random among the
+    /// stored ones
+    pub fn opponent<R: Rng + ?Sized>(&mut self, index: usize, rng: &mut R) -> &mut dyn Agent {
+        if self.agents.is_empty() {
+            self.build_agents();
+        }
+        match &mut self.agents[index] {
+            Some(agent) => agent.as_mut(),
+            None => self.snapshots.choose_mut(rng).expect(
+                "A snapshot is taken at the first epoch and leagues with snapshots keep one",
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_league() {
+        let league: League = "random=1,minimax:2=0.5,snapshot=2".parse().unwrap();
+        assert_eq!(
+            league.opponents,
+            vec![
+                (OpponentKind::Random, 1.0),
+                (OpponentKind::Minimax { depth: 2 }, 0.5),
+                (OpponentKind::Snapshot, 2.0),
+            ]
+        );
+        assert!("random".parse::<League>().is_err());
+        assert!("alphazero=1".parse::<League>().is_err());
+        assert!("random=0".parse::<League>().is_err());
+    }
+
+    #[test]
+    fn test_league_is_checked_when_read() {
+        let league = |json| serde_json::from_str::<League>(json);
+        assert!(league(
+            r#"{"opponents": [["Random", 1.0], ["Snapshot", 1.0]], "snapshot_every": 1, "max_snapshots": 1}"#
+        )
+        .is_ok());
+        assert!(league(r#"{"opponents": [], "snapshot_every": 1, "max_snapshots": 1}"#).is_err());
+        assert!(league(
+            r#"{"opponents": [["Random", 0.0]], "snapshot_every": 1, "max_snapshots": 1}"#
+        )
+        .is_err());
+        assert!(league(
+            r#"{"opponents": [["Snapshot", 1.0]], "snapshot_every": 1, "max_snapshots": 0}"#
+        )
+        .is_err());
+    }
+}
@@ -1,18 +1,208 @@
+use anyhow::{bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
 /// Representation of the state of a cell
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum CellState {
     Empty,
     X,
     O,
+    /// Obstacle nobody can play on, breaking the lines going through it
+    Blocked,
+}
+
+impl CellState {
+    /// Returns the other player, `Empty` and `Blocked` stay as they are
+    pub fn opponent(self) -> CellState {
+        match self {
+            CellState::X => CellState::O,
+            CellState::O => CellState::X,
+            cell => cell,
+        }
+    }
+
+    /// Returns whether the cell holds a player's symbol
+    pub fn is_mark(self) -> bool {
+        matches!(self, CellState::X | CellState::O)
+    }
+}
+
+/// Rule deciding who a completed line counts for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Variant {
+    /// Completing a line wins
+    #[default]
+    Standard,
+    /// Completing a line loses
+    Misere,
+}
+
+/// Dimensions of an m,n,k-game: `rows` along the y axis, `columns` along the x axis and the
+/// number of aligned symbols needed to win
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SavedBoardConfig")]
+pub struct BoardConfig {
+    pub rows: usize,
+    pub columns: usize,
+    pub win_condition: usize,
+    pub variant: Variant,
+    /// Moves pick a column and the mark falls to its lowest empty row, like in Connect Four
+    pub gravity: bool,
+    /// Lines wrap around the edges of the board, so rows, columns and diagonals are cyclic
+    pub toroidal: bool,
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self::square(3, 3)
+    }
+}
+
+/// Board config as read from a file, missing fields take their default value. Files written
+/// before boards could be rectangular give a `board_size` setting both dimensions.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SavedBoardConfig {
+    rows: Option<usize>,
+    columns: Option<usize>,
+    board_size: Option<usize>,
+    win_condition: Option<usize>,
+    variant: Variant,
+    gravity: bool,
+    toroidal: bool,
+}
+
+impl From<SavedBoardConfig> for BoardConfig {
+    fn from(saved: SavedBoardConfig) -> Self {
+        let default = BoardConfig::default();
+        Self {
+            rows: saved.rows.or(saved.board_size).unwrap_or(default.rows),
+            columns: saved
+                .columns
+                .or(saved.board_size)
+                .unwrap_or(default.columns),
+            win_condition: saved.win_condition.unwrap_or(default.win_condition),
+            variant: saved.variant,
+            gravity: saved.gravity,
+            toroidal: saved.toroidal,
+        }
+    }
+}
+
+impl BoardConfig {
+    pub fn square(size: usize, win_condition: usize) -> Self {
+        Self {
+            rows: size,
+            columns: size,
+            win_condition,
+            variant: Variant::Standard,
+            gravity: false,
+            toroidal: false,
+        }
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.rows * self.columns
+    }
+
+    /// Checks that the board has cells and that a line of `win_condition` symbols fits on it
+    pub fn validate(&self) -> Result<(), BoardConfigError> {
+        if self.rows == 0 || self.columns == 0 {
+            return Err(BoardConfigError::EmptyBoard {
+                rows: self.rows,
+                columns: self.columns,
+            });
+        }
+        if self.win_condition < 2 {
+            return Err(BoardConfigError::WinConditionTooShort {
+                win_condition: self.win_condition,
+            });
+        }
+        let longest_line = self.rows.max(self.columns);
+        if self.win_condition > longest_line {
+            return Err(BoardConfigError::WinConditionTooLong {
+                win_condition: self.win_condition,
+                longest_line,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BoardConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} board, {} in a row",
+            self.rows, self.columns, self.win_condition
+        )?;
+        if self.variant == Variant::Misere {
+            write!(f, " loses")?;
+        }
+        if self.gravity {
+            write!(f, ", with gravity")?;
+        }
+        if self.toroidal {
+            write!(f, ", on a torus")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reason a board configuration can't be played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoardConfigError {
+    EmptyBoard {
+        rows: usize,
+        columns: usize,
+    },
+    /// A single symbol can't make a line
+    WinConditionTooShort {
+        win_condition: usize,
+    },
+    /// No line of the board is long enough, so nobody can win
+    WinConditionTooLong {
+        win_condition: usize,
+        longest_line: usize,
+    },
 }
 
+impl fmt::Display for BoardConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BoardConfigError::EmptyBoard { rows, columns } => write!(
+                f,
+                "A {}x{} board has no cells, it needs at least one row and one column",
+                rows, columns
+            ),
+            BoardConfigError::WinConditionTooShort { win_condition } => write!(
+                f,
+                "Win condition of {} is too short, lines need at least 2 symbols",
+                win_condition
+            ),
+            BoardConfigError::WinConditionTooLong {
+                win_condition,
+                longest_line,
+            } => write!(
+                f,
+                "Win condition of {} is longer than the longest line of the board, {} cells",
+                win_condition, longest_line
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BoardConfigError {}
+
 /// Representation of a board of arbitrary size  
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Board {
     grid: Vec<Vec<CellState>>,
     turn: CellState,
-    size: usize,
-    win_condition_length: usize,
+    /// Position and player to move restored by `reset`
+    start: (Vec<Vec<CellState>>, CellState),
+    config: BoardConfig,
     /// first vector is for main diagonal and anti diagonal, then it's a vector of Coords
     diagonals_coords: Vec<Vec<Coords>>,
 }
@@ -41,7 +231,13 @@ fn generate_coords(
         .collect()
 }
 
-fn calculate_diagonals_coords(size: usize, minimal_length: usize) -> Vec<Vec<Coords>> {
+/// Returns every diagonal of at least `minimal_length` cells of a `columns` by `rows` grid, each
+/// running from one edge of the grid to the other
+fn calculate_diagonals_coords(
+    columns: usize,
+    rows: usize,
+    minimal_length: usize,
+) -> Vec<Vec<Coords>> {
     let mut all_diagonals = Vec::new();
     let mut main_diagonals = Vec::new();
     let mut anti_diagonals = Vec::new();
@@ -49,16 +245,24 @@ fn calculate_diagonals_coords(size: usize, minimal_length: usize) -> Vec<Vec<Coo
     let direction_main = || (1, 1);
     let direction_anti = || (1, -1);
 
-    let valid_indices = |start, end| (start..end).filter(move |&i| end - i >= minimal_length);
-
-    for start in valid_indices(0, size) {
-        for length in (minimal_length..=size - start).rev() {
-            main_diagonals.push(generate_coords(start, 0, length, direction_main));
-            anti_diagonals.push(generate_coords(start, size - 1, length, direction_anti));
-            if start > 0 {
-                main_diagonals.push(generate_coords(0, start, length, direction_main));
-                anti_diagonals.push(generate_coords(0, start, length, direction_anti));
-            }
+    // Main diagonals start on the top row or the left column, anti diagonals on the bottom row
+    // or the left column
+    let starts = (0..columns)
+        .map(|x_axis| (x_axis, 0))
+        .chain((1..rows).map(|y_axis| (0, y_axis)));
+    for (x_axis, y_axis) in starts {
+        let length = (columns - x_axis).min(rows - y_axis);
+        if length >= minimal_length {
+            main_diagonals.push(generate_coords(x_axis, y_axis, length, direction_main));
+        }
+    }
+    let starts = (0..columns)
+        .map(|x_axis| (x_axis, rows - 1))
+        .chain((0..rows - 1).map(|y_axis| (0, y_axis)));
+    for (x_axis, y_axis) in starts {
+        let length = (columns - x_axis).min(y_axis + 1);
+        if length >= minimal_length {
+            anti_diagonals.push(generate_coords(x_axis, y_axis, length, direction_anti));
         }
     }
 
@@ -68,13 +272,261 @@ fn calculate_diagonals_coords(size: usize, minimal_length: usize) -> Vec<Vec<Coo
     all_diagonals
 }
 
+/// Returns the symbol of the first run of `win_condition` identical marks along the cells, if
+/// there's any
+pub fn find_line(
+    cells: impl IntoIterator<Item = CellState>,
+    win_condition: usize,
+) -> Option<CellState> {
+    let mut count_consecutive = 0;
+    let mut previous_cell = CellState::Empty;
+    for cell in cells {
+        if cell == previous_cell && cell.is_mark() {
+            count_consecutive += 1;
+            if count_consecutive == win_condition - 1 {
+                return Some(cell);
+            }
+        } else {
+            count_consecutive = 0;
+            previous_cell = cell;
+        }
+    }
+    None
+}
+
+/// Returns the diagonals of a `columns` by `rows` torus, where they wrap around the edges. Each
+/// one is a cycle through lcm(columns, rows) cells, and gcd(columns, rows) of them go along
+/// each direction.
+fn calculate_toroidal_diagonals_coords(columns: usize, rows: usize) -> Vec<Vec<Coords>> {
+    let mut gcd = (columns, rows);
+    while gcd.1 != 0 {
+        gcd = (gcd.1, gcd.0 % gcd.1);
+    }
+    let (gcd, length) = (gcd.0, columns * rows / gcd.0);
+    // Cycles are told apart by the row of their cell on the left column
+    let main_diagonals = (0..gcd)
+        .map(|start| {
+            (0..length)
+                .map(|i| (i % columns, (start + i) % rows))
+                .collect()
+        })
+        .collect();
+    let anti_diagonals = (0..gcd)
+        .map(|start| {
+            (0..length)
+                .map(|i| (i % columns, (start + rows - i % rows) % rows))
+                .collect()
+        })
+        .collect();
+    vec![main_diagonals, anti_diagonals]
+}
+
+// Kept next to the line generation it mostly covers
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
     // TODO parametrize the tests
+    #[test]
+    fn test_position_round_trip() {
+        let config = BoardConfig::default();
+        let board = Board::from_position("X.O/.X./...", config).unwrap();
+        assert_eq!(board.get_grid()[2][0], CellState::O);
+        assert_eq!(board.get_current_player(), CellState::O);
+        assert_eq!(board.to_position(), "X.O/.X./...");
+        assert!(Board::from_position("XX./.../...", config).is_err());
+        assert!(Board::from_position("X.O/.X", config).is_err());
+
+        let board = Board::from_position("X..O/.X../....", config).unwrap();
+        assert_eq!(
+            board.config(),
+            BoardConfig {
+                rows: 3,
+                columns: 4,
+                ..config
+            }
+        );
+        assert_eq!(board.get_grid()[3][0], CellState::O);
+        assert_eq!(board.to_position(), "X..O/.X../....");
+    }
+
+    #[test]
+    fn test_blocked_cells_and_start_position() {
+        let config = BoardConfig::default();
+        let mut board = Board::from_position("X#./.O./...:O", config).unwrap();
+        assert_eq!(board.get_current_player(), CellState::O);
+        assert_eq!(board.to_position(), "X#./.O./...");
+        assert!(!board.is_valid_move(1, 0));
+        assert_eq!(board.get_possible_actions().len(), 6);
+        board.play_move(2, 0);
+        board.reset();
+        assert_eq!(board.to_position(), "X#./.O./...");
+        assert_eq!(board.get_current_player(), CellState::O);
+        assert!(Board::from_position("X../.../...:Z", config).is_err());
+
+        // The obstacle breaks the top row
+        let board = Board::from_position("X#X/OO./X..", config).unwrap();
+        assert_eq!(board.is_winner(), None);
+
+        // With gravity marks rest on obstacles
+        let config = BoardConfig {
+            gravity: true,
+            ..config
+        };
+        let mut board = Board::from_position(".../#../...", config).unwrap();
+        board.play_move(0, 0);
+        assert_eq!(board.get_grid()[0][0], CellState::X);
+    }
+
+    #[test]
+    fn test_toroidal_lines_wrap_around() {
+        let config = BoardConfig {
+            toroidal: true,
+            ..BoardConfig::square(4, 3)
+        };
+        let flat = BoardConfig {
+            toroidal: false,
+            ..config
+        };
+        // The row, the column and both diagonals only make a line across the edges
+        for position in [
+            "XX.X/..../..../....:O",
+            "X.../X.../..../X...:O",
+            "X.../.X../..../...X:O",
+            "...X/..X./..../X...:O",
+        ] {
+            let board = Board::from_position(position, config).unwrap();
+            assert_eq!(board.is_winner(), Some(CellState::X), "{}", position);
+            let board = Board::from_position(position, flat).unwrap();
+            assert_eq!(board.is_winner(), None, "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_toroidal_diagonals_cover_the_grid() {
+        // A 2x4 torus has 2 diagonals of 4 cells along each direction
+        let diagonals = calculate_toroidal_diagonals_coords(4, 2);
+        for direction in &diagonals {
+            assert_eq!(direction.len(), 2);
+            let mut cells: Vec<(usize, usize)> = direction.iter().flatten().copied().collect();
+            cells.sort();
+            cells.dedup();
+            assert_eq!(cells.len(), 8);
+        }
+        // A short column doesn't wrap onto itself
+        let config = BoardConfig {
+            rows: 2,
+            columns: 4,
+            win_condition: 3,
+            toroidal: true,
+            ..BoardConfig::default()
+        };
+        let board = Board::from_position("X.../X...:O", config).unwrap();
+        assert_eq!(board.is_winner(), None);
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        let config = |rows, columns, win_condition| BoardConfig {
+            rows,
+            columns,
+            win_condition,
+            ..BoardConfig::default()
+        };
+        assert!(Board::try_new(config(3, 3, 3)).is_ok());
+        assert!(Board::try_new(config(2, 5, 5)).is_ok());
+        assert_eq!(
+            Board::try_new(config(0, 3, 3)).unwrap_err(),
+            BoardConfigError::EmptyBoard {
+                rows: 0,
+                columns: 3
+            }
+        );
+        for win_condition in [0, 1] {
+            assert_eq!(
+                Board::try_new(config(3, 3, win_condition)).unwrap_err(),
+                BoardConfigError::WinConditionTooShort { win_condition }
+            );
+        }
+        assert_eq!(
+            Board::try_new(config(3, 4, 5)).unwrap_err(),
+            BoardConfigError::WinConditionTooLong {
+                win_condition: 5,
+                longest_line: 4
+            }
+        );
+        assert!(Board::from_position("X.O/.X./...", config(3, 3, 4)).is_err());
+    }
+
+    #[test]
+    fn test_rectangular_board_lines() {
+        let config = BoardConfig {
+            rows: 2,
+            columns: 5,
+            win_condition: 2,
+            ..BoardConfig::default()
+        };
+        let mut board = Board::new(config);
+        assert_eq!(board.get_possible_actions().len(), 10);
+        // Anti diagonal through the last column
+        board.play_move(4, 0);
+        board.play_move(0, 0);
+        board.play_move(3, 1);
+        assert_eq!(board.is_winner(), Some(CellState::X));
+
+        let mut board = Board::new(BoardConfig {
+            win_condition: 4,
+            ..config
+        });
+        for x_axis in 0..3 {
+            board.play_move(x_axis, 0);
+            board.play_move(x_axis, 1);
+        }
+        assert_eq!(board.is_winner(), None);
+        board.play_move(3, 0);
+        assert_eq!(board.is_winner(), Some(CellState::X));
+    }
+
+    #[test]
+    fn test_misere_line_loses() {
+        let config = BoardConfig {
+            variant: Variant::Misere,
+            ..BoardConfig::default()
+        };
+        let board = Board::from_position("XXX/OO./...", config).unwrap();
+        assert_eq!(board.is_winner(), Some(CellState::O));
+        let board = Board::from_position("XX./OOO/X..", config).unwrap();
+        assert_eq!(board.is_winner(), Some(CellState::X));
+    }
+
+    #[test]
+    fn test_gravity_drops_marks() {
+        let config = BoardConfig {
+            rows: 6,
+            columns: 7,
+            win_condition: 4,
+            gravity: true,
+            ..BoardConfig::default()
+        };
+        let mut board = Board::new(config);
+        assert_eq!(board.get_possible_actions().len(), 7);
+        board.play_move(3, 0);
+        board.play_move(3, 2);
+        assert_eq!(board.get_grid()[3][5], CellState::X);
+        assert_eq!(board.get_grid()[3][4], CellState::O);
+        assert!(board.get_possible_actions().contains(&(3, 3)));
+        for _ in 0..4 {
+            board.play_move(3, 0);
+        }
+        assert!(!board.is_valid_move(3, 0));
+        assert_eq!(board.get_possible_actions().len(), 6);
+        assert!(Board::from_position("X.../..../....", config).is_err());
+        assert!(Board::from_position("..../..../X...", config).is_ok());
+    }
+
     #[test]
     fn test_diagonal_2_2() {
-        let diagonals = calculate_diagonals_coords(2, 2);
+        let diagonals = calculate_diagonals_coords(2, 2, 2);
         assert_eq!(diagonals[0].len(), 1);
         assert_eq!(diagonals[1].len(), 1);
         println!("{:?}", diagonals);
@@ -82,7 +534,7 @@ mod tests {
 
     #[test]
     fn test_diagonal_3_3() {
-        let diagonals = calculate_diagonals_coords(3, 3);
+        let diagonals = calculate_diagonals_coords(3, 3, 3);
         assert_eq!(diagonals[0].len(), 1);
         assert_eq!(diagonals[1].len(), 1);
         println!("{:?}", diagonals);
@@ -90,50 +542,226 @@ mod tests {
 
     #[test]
     fn test_diagonal_3_2() {
-        let diagonals = calculate_diagonals_coords(3, 2);
-        assert_eq!(diagonals[0].len(), 4);
-        assert_eq!(diagonals[1].len(), 4);
+        let diagonals = calculate_diagonals_coords(3, 3, 2);
+        assert_eq!(diagonals[0].len(), 3);
+        assert_eq!(diagonals[1].len(), 3);
         println!("{:?}", diagonals);
     }
 
     #[test]
     fn test_diagonal_10_2() {
-        let diagonals = calculate_diagonals_coords(10, 2);
-        assert_eq!(diagonals[0].len(), 81);
-        assert_eq!(diagonals[1].len(), 81);
-        // Note: the theoretical number here should be 45 for each, the reason it's 45 is because
-        // we are double counting some diagonals. For example, the diagonal that goes along the i_i
-        // line is counted several times because you would have [(0,0), (1,1)], [(0, 0), (1, 1),
-        // (2, 2)], etc. TODO fix that
+        let diagonals = calculate_diagonals_coords(10, 10, 2);
+        // Every diagonal is listed once, with all its cells
+        assert_eq!(diagonals[0].len(), 17);
+        assert_eq!(diagonals[1].len(), 17);
+        assert!(diagonals[1].contains(&(0..6).map(|x_axis| (x_axis, 5 - x_axis)).collect()));
         println!("{:?}", diagonals);
     }
+
+    #[test]
+    fn test_diagonal_rectangle() {
+        let diagonals = calculate_diagonals_coords(5, 3, 3);
+        assert_eq!(diagonals[0].len(), 3);
+        assert_eq!(diagonals[1].len(), 3);
+        assert!(diagonals
+            .iter()
+            .flatten()
+            .all(|diagonal| diagonal.len() == 3));
+        assert!(diagonals[1].contains(&vec![(2, 2), (3, 1), (4, 0)]));
+    }
 }
 
 impl Board {
-    /// Creates a new board
-    pub fn new(size: usize, win_condition_length: usize) -> Self {
+    /// Creates a new board, the configuration has to be valid, see `try_new`
+    pub fn new(config: BoardConfig) -> Self {
+        debug_assert_eq!(config.validate(), Ok(()));
+        let grid = vec![vec![CellState::Empty; config.rows]; config.columns];
         Self {
-            grid: vec![vec![CellState::Empty; size]; size],
+            start: (grid.clone(), CellState::X),
+            grid,
             turn: CellState::X,
-            size,
-            win_condition_length,
-            diagonals_coords: calculate_diagonals_coords(size, win_condition_length),
+            config,
+            diagonals_coords: if config.toroidal {
+                calculate_toroidal_diagonals_coords(config.columns, config.rows)
+            } else {
+                calculate_diagonals_coords(config.columns, config.rows, config.win_condition)
+            },
         }
     }
 
-    /// Reset player turn and grid
+    /// Creates a new board, or tells why the configuration can't be played
+    pub fn try_new(config: BoardConfig) -> Result<Self, BoardConfigError> {
+        config.validate()?;
+        Ok(Self::new(config))
+    }
+
+    /// Parses a position written row by row with `X`, `O`, `.` for empty cells and `#` for
+    /// blocked ones, rows are separated by `/`, e.g. `X.O/.#./...`. Without separators the board
+    /// is square. The dimensions come from the position and the rules from `config`. The player to
+    /// move can follow a `:`, e.g. `X.O/.#./...:O`, otherwise X starts and the player to move
+    /// follows from the number of symbols. The position becomes the start the board resets to.
+    pub fn from_position(position: &str, config: BoardConfig) -> anyhow::Result<Self> {
+        let (layout, side) = match position.split_once(':') {
+            Some((layout, side)) => {
+                let side = match side.trim() {
+                    "X" | "x" => CellState::X,
+                    "O" | "o" => CellState::O,
+                    _ => bail!(
+                        "Unknown player to move `{}` in position `{}`",
+                        side,
+                        position
+                    ),
+                };
+                (layout, Some(side))
+            }
+            None => (position, None),
+        };
+        let parse_row = |row: &str| {
+            row.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    'X' | 'x' => Ok(CellState::X),
+                    'O' | 'o' => Ok(CellState::O),
+                    '.' | '_' | '-' => Ok(CellState::Empty),
+                    '#' => Ok(CellState::Blocked),
+                    _ => bail!("Unknown cell `{}` in position `{}`", c, position),
+                })
+                .collect::<anyhow::Result<Vec<CellState>>>()
+        };
+        let rows: Vec<Vec<CellState>> = if layout.contains('/') {
+            layout
+                .split('/')
+                .map(parse_row)
+                .collect::<anyhow::Result<_>>()?
+        } else {
+            let cells = parse_row(layout)?;
+            let size = (cells.len() as f64).sqrt().round() as usize;
+            ensure!(
+                size > 0 && size * size == cells.len(),
+                "Position `{}` has {} cells, which is not a square board",
+                position,
+                cells.len()
+            );
+            cells.chunks(size).map(<[CellState]>::to_vec).collect()
+        };
+        let columns = rows[0].len();
+        ensure!(
+            columns > 0 && rows.iter().all(|row| row.len() == columns),
+            "Rows of position `{}` don't have the same number of cells",
+            position
+        );
+        let config = BoardConfig {
+            rows: rows.len(),
+            columns,
+            ..config
+        };
+        config.validate()?;
+        if config.gravity {
+            // Rows are listed top to bottom, a mark can't lie above an empty cell
+            for pair in rows.windows(2) {
+                ensure!(
+                    (0..columns)
+                        .all(|x_axis| !pair[0][x_axis].is_mark()
+                            || pair[1][x_axis] != CellState::Empty),
+                    "Position `{}` has marks floating above empty cells",
+                    position
+                );
+            }
+        }
+        let cells: Vec<CellState> = rows.iter().flatten().copied().collect();
+        let n_x = cells.iter().filter(|&&cell| cell == CellState::X).count();
+        let n_o = cells.iter().filter(|&&cell| cell == CellState::O).count();
+        // Puzzles may start anywhere once the player to move is given
+        ensure!(
+            side.is_some() || n_x == n_o || n_x == n_o + 1,
+            "Position `{}` can't be reached, X starts and players alternate",
+            position
+        );
+
+        // Rows are displayed top to bottom along the y axis, like in the GUI
+        let grid = (0..columns)
+            .map(|x_axis| rows.iter().map(|row| row[x_axis]).collect())
+            .collect();
+        let mut board = Self::from_grid(grid, config);
+        if let Some(side) = side {
+            board.turn = side;
+            board.start.1 = side;
+        }
+        Ok(board)
+    }
+
+    /// Creates the board the games start from, `position` if any, see `from_position`, otherwise
+    /// the empty board. A position where the game is already over can't be a start.
+    pub fn from_start(position: Option<&str>, config: BoardConfig) -> anyhow::Result<Self> {
+        let Some(position) = position else {
+            return Ok(Self::try_new(config)?);
+        };
+        let board = Self::from_position(position, config)?;
+        ensure!(
+            board.is_winner().is_none() && !board.get_possible_actions().is_empty(),
+            "Start position `{}` is already over, there's no move left to play",
+            position
+        );
+        Ok(board)
+    }
+
+    /// Creates a board holding the grid with the rules of `config`, X starts so the player to
+    /// move follows from the number of symbols. The grid becomes the start the board resets to.
+    pub fn from_grid(grid: Vec<Vec<CellState>>, config: BoardConfig) -> Self {
+        let count = |player| {
+            grid.iter()
+                .flatten()
+                .filter(|&&cell| cell == player)
+                .count()
+        };
+        let turn = if count(CellState::X) > count(CellState::O) {
+            CellState::O
+        } else {
+            CellState::X
+        };
+        let mut board = Self::new(BoardConfig {
+            rows: grid[0].len(),
+            columns: grid.len(),
+            ..config
+        });
+        board.start = (grid.clone(), turn);
+        board.grid = grid;
+        board.turn = turn;
+        board
+    }
+
+    /// Writes the grid in the format read by `from_position`
+    pub fn to_position(&self) -> String {
+        (0..self.config.rows)
+            .map(|y_axis| {
+                (0..self.config.columns)
+                    .map(|x_axis| match self.grid[x_axis][y_axis] {
+                        CellState::X => 'X',
+                        CellState::O => 'O',
+                        CellState::Empty => '.',
+                        CellState::Blocked => '#',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Reset player turn and grid to the start position
     pub fn reset(&mut self) {
-        self.grid = vec![vec![CellState::Empty; self.size]; self.size];
-        self.turn = CellState::X;
+        self.grid = self.start.0.clone();
+        self.turn = self.start.1;
     }
 
-    /// Play move at position x_axis, y_axis
+    /// Play move at position x_axis, y_axis, with gravity the mark falls down column x_axis
+    /// whatever y_axis is
     pub fn play_move(&mut self, x_axis: usize, y_axis: usize) {
         /* println!(
             "Player {:?} attempting to play at position ({},{})",
             self.turn, x_axis, y_axis
         ); */
         if self.is_valid_move(x_axis, y_axis) {
+            let (x_axis, y_axis) = self.landing_cell(x_axis, y_axis);
             self.grid[x_axis][y_axis] = self.turn;
             /* println!(
                 "New value at [{}, {}]: {:?}",
@@ -143,9 +771,33 @@ impl Board {
         };
     }
 
-    /// Checks if move is valid
+    /// Checks if move is valid, with gravity any cell of a column which isn't full is valid, the
+    /// mark lands on `landing_cell`
     pub fn is_valid_move(&self, x_axis: usize, y_axis: usize) -> bool {
-        (x_axis < self.size && y_axis < self.size) && self.grid[x_axis][y_axis] == CellState::Empty
+        if self.config.gravity {
+            return x_axis < self.config.columns && self.drop_row(x_axis).is_some();
+        }
+        (x_axis < self.config.columns && y_axis < self.config.rows)
+            && self.grid[x_axis][y_axis] == CellState::Empty
+    }
+
+    /// Cell where a move at x_axis, y_axis puts the mark
+    pub fn landing_cell(&self, x_axis: usize, y_axis: usize) -> (usize, usize) {
+        match self.config.gravity {
+            true => (x_axis, self.drop_row(x_axis).unwrap_or(y_axis)),
+            false => (x_axis, y_axis),
+        }
+    }
+
+    /// Lowest empty row of the column, above its marks and obstacles, rows are numbered from the
+    /// top
+    fn drop_row(&self, x_axis: usize) -> Option<usize> {
+        let column = &self.grid[x_axis];
+        column
+            .iter()
+            .position(|&cell| cell != CellState::Empty)
+            .unwrap_or(column.len())
+            .checked_sub(1)
     }
 
     /// Returns current player
@@ -153,13 +805,14 @@ impl Board {
         self.turn
     }
 
+    /// Overrides the player to move
+    pub fn set_current_player(&mut self, player: CellState) {
+        self.turn = player;
+    }
+
     /// Update player turn
     fn next_turn(&mut self) {
-        self.turn = if self.turn == CellState::X {
-            CellState::O
-        } else {
-            CellState::X
-        };
+        self.turn = self.turn.opponent();
     }
 
     /// Returns whether the board is full, an alternative approach could be to count the number of
@@ -175,8 +828,18 @@ impl Board {
         true
     }
 
-    /// Returns the winner of the game if there's any
+    /// Returns the winner of the game if there's any, in the misère variant the player who
+    /// completed a line loses
     pub fn is_winner(&self) -> Option<CellState> {
+        let owner = self.find_line_owner()?;
+        Some(match self.config.variant {
+            Variant::Standard => owner,
+            Variant::Misere => owner.opponent(),
+        })
+    }
+
+    /// Returns the player owning a complete line if there's any
+    fn find_line_owner(&self) -> Option<CellState> {
         // println!("Checking for winner");
         // println!("Grid: {:?}", self.grid);
 
@@ -187,7 +850,7 @@ impl Board {
             }
         }
 
-        for index in 0..self.size {
+        for index in 0..self.config.rows {
             let sequence: Vec<CellState> = self.grid.iter().map(|seq| seq[index]).collect();
             // println!("sequence: {:?}", sequence);
             if let Some(winner) = self.find_winner(&sequence) {
@@ -212,30 +875,32 @@ impl Board {
         None
     }
 
-    /// Given a sequence, returns the winner if there's any
+    /// Given a sequence, returns the winner if there's any. On a torus the sequence is a cycle,
+    /// so its first cells also follow its last ones, unless it's too short to hold a line.
     fn find_winner(&self, sequence: &[CellState]) -> Option<CellState> {
-        let mut count_consecutive = 0;
-        let mut previous_cell = CellState::Empty;
-        for cell in sequence.iter() {
-            if *cell == previous_cell && *cell != CellState::Empty {
-                count_consecutive += 1;
-                // println!("{}", count_consecutive);
-                if count_consecutive == self.win_condition_length - 1 {
-                    return Some(*cell);
-                }
-            } else {
-                count_consecutive = 0;
-                previous_cell = *cell;
-            }
-        }
-        None
+        let win_condition = self.config.win_condition;
+        let wrap = if self.config.toroidal && sequence.len() >= win_condition {
+            win_condition - 1
+        } else {
+            0
+        };
+        find_line(
+            sequence.iter().chain(&sequence[..wrap]).copied(),
+            win_condition,
+        )
     }
 
+    /// Returns the empty cells, or with gravity the landing cell of every column which isn't full
     pub fn get_possible_actions(&self) -> Coords {
+        if self.config.gravity {
+            return (0..self.config.columns)
+                .filter_map(|x_axis| Some((x_axis, self.drop_row(x_axis)?)))
+                .collect();
+        }
         let mut possible_actions = Vec::new();
 
-        for x_axis in 0..self.size {
-            for y_axis in 0..self.size {
+        for x_axis in 0..self.config.columns {
+            for y_axis in 0..self.config.rows {
                 if self.grid[x_axis][y_axis] == CellState::Empty {
                     possible_actions.push((x_axis, y_axis));
                 }
@@ -247,4 +912,8 @@ impl Board {
     pub fn get_grid(&self) -> Vec<Vec<CellState>> {
         self.grid.clone()
     }
+
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
 }
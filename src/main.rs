@@ -1,8 +1,52 @@
-use clap::Parser;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
 
 mod agent;
+mod checkpoint;
+mod convergence;
+mod cube;
+mod dqn;
+mod enumeration;
+mod evaluation;
+mod experiment;
+mod exploitability;
 mod gui;
+mod inspect;
+mod league;
 mod logic;
+mod mcts;
+mod minimax;
+mod nn;
+mod notakto;
+mod planning;
+mod policy;
+mod replay;
+mod search;
+mod stats;
+mod tournament;
+mod ultimate;
+mod wild;
+
+use agent::{train, train_self_play, AgentSpec, Game, QTable, TrainingOptions, TrainingState};
+use checkpoint::{load_json, save_json, CheckpointConfig};
+use convergence::{EarlyStopping, Plateau};
+use cube::Cube;
+use experiment::{AgentConfig, ExperimentConfig, LearnerConfig, OutputConfig};
+use league::{League, OpponentKind};
+use logic::{Board, BoardConfig, Variant};
+use notakto::Notakto;
+use policy::{Exploration, Policy};
+use replay::{ReplayConfig, Sampling};
+use search::{SearchConfig, SearchSpace, Strategy};
+use tournament::Participant;
+use ultimate::UltimateBoard;
+use wild::WildBoard;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -19,13 +63,778 @@ struct Args {
 
     #[clap(long)]
     evaluation: bool,
+
+    /// Train the Q-learning agent without the GUI and save its table to --output
+    #[clap(long)]
+    train: bool,
+
+    /// Continue the training saved in a checkpoint, the other training options are ignored
+    #[clap(long)]
+    resume: Option<PathBuf>,
+
+    /// File the trained Q-table is written to
+    #[clap(long, default_value = "q_table.json")]
+    output: PathBuf,
+
+    /// File the metrics of every epoch of the training are written to
+    #[clap(long)]
+    metrics: Option<PathBuf>,
+
+    /// Read the experiment from this JSON file, it replaces the board, agent, training, seed and
+    /// output options
+    #[clap(long)]
+    config: Option<PathBuf>,
+
+    /// Write the experiment described by the options to this JSON file and exit
+    #[clap(long)]
+    save_config: Option<PathBuf>,
+
+    /// Use the Q-table saved in this file instead of training one
+    #[clap(long)]
+    q_table: Option<PathBuf>,
+
+    /// Print the Q-value and visit count of every legal move of a position, written row by row
+    /// with `X`, `O` and `.` for empty cells, e.g. `X.O/.X./...`
+    #[clap(long)]
+    inspect: Option<String>,
+
+    /// Print statistics of the Q-table: states, coverage of the reachable positions, value
+    /// histogram and memory
+    #[clap(long)]
+    stats: bool,
+
+    /// Count every position reachable on the board, per ply and by outcome
+    #[clap(long)]
+    enumerate: bool,
+
+    /// Count rotated and reflected positions once with --enumerate
+    #[clap(long)]
+    symmetry: bool,
+
+    /// Compute the optimal Q-values against a fixed opponent by value iteration and save them to
+    /// --output, the opponent is `random`, `minimax:<depth>` or `table:<path>`. The table given
+    /// with --q-table, if any, is compared to the plan.
+    #[clap(long)]
+    plan: Option<String>,
+
+    /// Discount factor of the value iteration
+    #[clap(long, default_value_t = 0.99)]
+    plan_gamma: f64,
+
+    /// Search the whole game tree for the best response to the greedy policy of the Q-table, and
+    /// list the positions where it deviates from optimal play
+    #[clap(long)]
+    exploitability: bool,
+
+    /// Play a round robin between agents and rate them, entries are `random`, `heuristic`,
+    /// `minimax:<depth>`, `mcts:<iterations>` or `table:<path>` for a saved Q-table, optionally
+    /// named with `<name>=`. Every pair plays --n-games games.
+    #[clap(long, num_args = 2.., value_delimiter = ',')]
+    tournament: Option<Vec<Participant>>,
+
+    /// File the tournament results are exported to, as JSON for a `.json` extension and CSV
+    /// otherwise
+    #[clap(long)]
+    export: Option<PathBuf>,
+
+    /// Search the hyperparameters of the Q-learning training over the space described in this
+    /// JSON file, e.g. `{"alpha": [[0.9, 0.1], [0.5]], "n_epoch": [100, 200]}`
+    #[clap(long)]
+    search: Option<PathBuf>,
+
+    #[clap(long, value_enum, default_value_t = SearchKind::Grid)]
+    search_strategy: SearchKind,
+
+    /// Number of trials of the random search
+    #[clap(long, default_value_t = 20)]
+    n_trials: usize,
+
+    /// Number of successive halving rounds of the search, 1 trains every trial with its full
+    /// budget
+    #[clap(long, default_value_t = 1)]
+    halving_rounds: usize,
+
+    /// Opponent the trials are evaluated against, `random`, `heuristic` or `minimax:<depth>`
+    #[clap(long, value_parser = parse_evaluation_opponent, default_value = "heuristic")]
+    baseline: OpponentKind,
+
+    /// Number of evaluation games of every trial
+    #[clap(long, default_value_t = 200)]
+    eval_games: usize,
+
+    /// File the ranked trials are written to
+    #[clap(long, default_value = "search_summary.json")]
+    summary: PathBuf,
+
+    /// Largest number of non terminal positions enumerated before giving up
+    #[clap(long, default_value_t = 10_000_000)]
+    max_states: usize,
+
+    /// File the training state is periodically saved to, and on Ctrl-C. Without it Ctrl-C saves
+    /// the state next to --output, e.g. q_table.checkpoint.json
+    #[clap(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Number of epochs between two checkpoints
+    #[clap(long, default_value_t = 10)]
+    checkpoint_every: usize,
+
+    /// Number of epochs of the Q-learning training
+    #[clap(long, default_value_t = 200)]
+    n_epoch: usize,
+
+    /// Number of games per epoch of the Q-learning training
+    #[clap(long, default_value_t = 1000)]
+    epoch_games: usize,
+
+    /// Seed of the random generator, runs with the same seed are identical
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Stop the training once no Q-value changes more than this during an epoch
+    #[clap(long)]
+    stop_q_change: Option<f64>,
+
+    /// Stop the training once the greedy action changes in less than this fraction of the states
+    /// during an epoch
+    #[clap(long)]
+    stop_policy_change: Option<f64>,
+
+    /// Stop the training once the score of the greedy policy against this opponent plateaus,
+    /// `random`, `heuristic` or `minimax:<depth>`
+    #[clap(long, value_parser = parse_evaluation_opponent)]
+    stop_plateau: Option<OpponentKind>,
+
+    /// Number of games of the evaluation done after every epoch for --stop-plateau
+    #[clap(long, default_value_t = 200)]
+    plateau_games: usize,
+
+    /// Smallest improvement of the best score that resets the plateau
+    #[clap(long, default_value_t = 0.01)]
+    plateau_min_delta: f64,
+
+    /// Number of consecutive epochs a stopping criterion must hold
+    #[clap(long, default_value_t = 5)]
+    patience: usize,
+
+    /// Play against a Monte Carlo Tree Search agent
+    #[clap(long)]
+    mcts: bool,
+
+    /// Play against a deep Q-learning agent
+    #[clap(long)]
+    dqn: bool,
+
+    /// Number of MCTS iterations per move
+    #[clap(long, default_value_t = 5000)]
+    mcts_iterations: usize,
+
+    /// Time limit per MCTS move in milliseconds, takes precedence over the iterations
+    #[clap(long)]
+    mcts_time_ms: Option<u64>,
+
+    /// Guide MCTS rollouts with the win/block heuristic instead of random moves
+    #[clap(long)]
+    mcts_heuristic_rollouts: bool,
+
+    /// Number of training games of the deep Q-learning agent
+    #[clap(long, default_value_t = 20000)]
+    dqn_games: usize,
+
+    /// Replay buffer used by the Q-learning agent, also sets the sampling of the DQN agent
+    #[clap(long, value_enum)]
+    replay: Option<ReplayKind>,
+
+    #[clap(
+        long,
+        default_value_t = 10000,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    replay_capacity: usize,
+
+    #[clap(long, default_value_t = 32)]
+    replay_batch_size: usize,
+
+    /// Exploration strategy of the Q-learning agent during training
+    #[clap(long, value_enum, default_value_t = ExplorationKind::EpsilonGreedy)]
+    exploration: ExplorationKind,
+
+    #[clap(long, default_value_t = 1.0)]
+    initial_temperature: f64,
+
+    #[clap(long, default_value_t = 0.01)]
+    final_temperature: f64,
+
+    #[clap(long, default_value_t = 1.0)]
+    ucb_exploration: f64,
+
+    /// Train the Q-learning agent against a mix of opponents instead of itself, as a comma
+    /// separated list of `kind=weight` with kinds `random`, `heuristic`, `minimax:<depth>` and
+    /// `snapshot`, e.g. `random=1,minimax:2=1,snapshot=2`
+    #[clap(long)]
+    league: Option<League>,
+
+    /// Number of epochs between two snapshots of the learner in the league
+    #[clap(long, default_value_t = 10)]
+    snapshot_every: usize,
+
+    /// Depth of the minimax agent
+    #[clap(long, default_value_t = 9)]
+    minimax_depth: usize,
+
+    /// Epsilon kept by trained agents when playing, they play greedily by default
+    #[clap(long)]
+    play_epsilon: Option<f64>,
+
+    /// Agent evaluated with --evaluation
+    #[clap(long, value_enum, default_value_t = AgentKind::QTable)]
+    agent: AgentKind,
+
+    /// Opponent of the evaluated agent
+    #[clap(long, value_enum, default_value_t = AgentKind::Random)]
+    opponent: AgentKind,
+
+    /// Play ultimate tic-tac-toe instead of the board options: --train trains a Q-table by
+    /// self-play, --gui plays against the table of --q-table if given, otherwise the table is
+    /// evaluated against a random player
+    #[clap(long)]
+    ultimate: bool,
+
+    /// Play 3D tic-tac-toe on a cube with the side and the win condition of the square board,
+    /// lines go in all 13 directions, e.g. Qubic with 4 and 4. The modes are those of --ultimate.
+    #[clap(long)]
+    cube: bool,
+
+    /// Play wild tic-tac-toe on the board, both players may place X or O and whoever completes a
+    /// line wins. The modes are those of --ultimate.
+    #[clap(long)]
+    wild: bool,
+
+    /// Play Notakto on this number of boards of the environment: both players place X, a board
+    /// with a line is dead and whoever kills the last board loses. The modes are those of
+    /// --ultimate.
+    #[clap(long)]
+    notakto: Option<usize>,
+
+    /// Number of rows and columns of the board
+    #[clap(long, default_value_t = 3)]
+    board_size: usize,
+
+    /// Number of rows of the board, overrides --board-size
+    #[clap(long)]
+    rows: Option<usize>,
+
+    /// Number of columns of the board, overrides --board-size
+    #[clap(long)]
+    columns: Option<usize>,
+
+    #[clap(long, default_value_t = 3)]
+    win_condition: usize,
+
+    /// Play the misère variant, where completing a line loses
+    #[clap(long)]
+    misere: bool,
+
+    /// Marks fall to the lowest empty row of the played column, like in Connect Four
+    #[clap(long)]
+    gravity: bool,
+
+    /// Lines wrap around the edges of the board, so rows, columns and diagonals are cyclic
+    #[clap(long)]
+    toroidal: bool,
+
+    /// Position the games start from, which sets the dimensions of the board, e.g. "X#./.O./..."
+    /// with # for blocked cells, followed by ":O" to give O the move
+    #[clap(long)]
+    start: Option<String>,
+
+    /// Number of evaluation games
+    #[clap(long, default_value_t = 1000)]
+    n_games: usize,
+}
+
+fn parse_evaluation_opponent(kind: &str) -> anyhow::Result<OpponentKind> {
+    match kind.parse()? {
+        OpponentKind::Snapshot => anyhow::bail!("Snapshots can't be evaluation opponents"),
+        opponent => Ok(opponent),
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SearchKind {
+    Grid,
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ReplayKind {
+    Uniform,
+    Prioritized,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExplorationKind {
+    EpsilonGreedy,
+    Boltzmann,
+    Ucb,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AgentKind {
+    Random,
+    Heuristic,
+    QTable,
+    Dqn,
+    Mcts,
+    Minimax,
 }
 
-fn main() {
+impl Args {
+    fn replay_config(&self) -> Option<ReplayConfig> {
+        self.replay.map(|kind| ReplayConfig {
+            capacity: self.replay_capacity,
+            batch_size: self.replay_batch_size,
+            sampling: match kind {
+                ReplayKind::Uniform => Sampling::Uniform,
+                ReplayKind::Prioritized => Sampling::Prioritized {
+                    alpha: 0.6,
+                    beta: 0.4,
+                },
+            },
+        })
+    }
+
+    fn exploration(&self) -> Exploration {
+        match self.exploration {
+            ExplorationKind::EpsilonGreedy => Exploration::EpsilonGreedy,
+            ExplorationKind::Boltzmann => Exploration::Boltzmann {
+                initial_temperature: self.initial_temperature,
+                final_temperature: self.final_temperature,
+            },
+            ExplorationKind::Ucb => Exploration::Ucb {
+                exploration: self.ucb_exploration,
+            },
+        }
+    }
+
+    fn play_policy(&self) -> Policy {
+        match self.play_epsilon {
+            Some(epsilon) => Policy::EpsilonGreedy { epsilon },
+            None => Policy::Greedy,
+        }
+    }
+
+    /// First mode given which only plays or analyses games from the empty board
+    fn empty_board_mode(&self) -> Option<&'static str> {
+        [
+            ("--ultimate", self.ultimate),
+            ("--cube", self.cube),
+            ("--wild", self.wild),
+            ("--notakto", self.notakto.is_some()),
+            ("--plan", self.plan.is_some()),
+            ("--exploitability", self.exploitability),
+            ("--enumerate", self.enumerate),
+            ("--stats", self.stats),
+        ]
+        .into_iter()
+        .find_map(|(flag, is_set)| is_set.then_some(flag))
+    }
+
+    /// Experiment loaded from --config, or described by the options
+    fn experiment(&self) -> anyhow::Result<ExperimentConfig> {
+        if let Some(path) = &self.config {
+            return ExperimentConfig::load(path);
+        }
+        let mut environment = BoardConfig {
+            rows: self.rows.unwrap_or(self.board_size),
+            columns: self.columns.unwrap_or(self.board_size),
+            win_condition: self.win_condition,
+            variant: if self.misere {
+                Variant::Misere
+            } else {
+                Variant::Standard
+            },
+            gravity: self.gravity,
+            toroidal: self.toroidal,
+        };
+        if let Some(position) = &self.start {
+            environment = Board::from_position(position, environment)?.config();
+        }
+        Ok(ExperimentConfig {
+            name: None,
+            environment,
+            start: self.start.clone(),
+            agent: self.agent_config(self.agent),
+            learner: LearnerConfig {
+                n_epoch: self.n_epoch,
+                n_games: self.epoch_games,
+                exploration: self.exploration(),
+                replay: self.replay_config(),
+                early_stopping: self.early_stopping(),
+                play_policy: self.play_policy(),
+            },
+            opponents: self.league.clone().map(|league| League {
+                snapshot_every: self.snapshot_every,
+                ..league
+            }),
+            seed: self.seed,
+            outputs: OutputConfig {
+                table: self.output.clone(),
+                metrics: self.metrics.clone(),
+                checkpoint: self.checkpoint.clone().map(|path| CheckpointConfig {
+                    path,
+                    every: self.checkpoint_every,
+                }),
+            },
+            ..ExperimentConfig::default()
+        })
+    }
+
+    fn early_stopping(&self) -> Option<EarlyStopping> {
+        if self.stop_q_change.is_none()
+            && self.stop_policy_change.is_none()
+            && self.stop_plateau.is_none()
+        {
+            return None;
+        }
+        Some(EarlyStopping {
+            max_q_change: self.stop_q_change,
+            policy_change: self.stop_policy_change,
+            plateau: self.stop_plateau.map(|opponent| Plateau {
+                opponent,
+                n_games: self.plateau_games,
+                min_delta: self.plateau_min_delta,
+            }),
+            patience: self.patience,
+        })
+    }
+
+    fn agent_config(&self, kind: AgentKind) -> AgentConfig {
+        match kind {
+            AgentKind::Random => AgentConfig::Random,
+            AgentKind::Heuristic => AgentConfig::Heuristic,
+            AgentKind::QTable => match &self.q_table {
+                Some(path) => AgentConfig::SavedQTable { path: path.clone() },
+                None => AgentConfig::QTable,
+            },
+            AgentKind::Dqn => AgentConfig::Dqn {
+                n_games: self.dqn_games,
+            },
+            AgentKind::Mcts => AgentConfig::Mcts {
+                iterations: self.mcts_iterations,
+                time_ms: self.mcts_time_ms,
+                heuristic_rollouts: self.mcts_heuristic_rollouts,
+            },
+            AgentKind::Minimax => AgentConfig::Minimax {
+                depth: self.minimax_depth,
+            },
+        }
+    }
+
+    /// Agent of the given kind, trained as described by the experiment
+    fn agent_spec(&self, kind: AgentKind, experiment: &ExperimentConfig) -> AgentSpec {
+        ExperimentConfig {
+            agent: self.agent_config(kind),
+            ..experiment.clone()
+        }
+        .agent_spec()
+    }
+}
+
+fn rng(experiment: &ExperimentConfig) -> ChaCha8Rng {
+    match experiment.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    }
+}
+
+/// Runs or resumes a training, the table is only written once the training completed
+fn run_training(args: &Args, experiment: &ExperimentConfig) -> anyhow::Result<()> {
+    checkpoint::install_interrupt_handler()?;
+    let state = match &args.resume {
+        Some(path) => TrainingState::load(path)?,
+        None => TrainingState::from_experiment(experiment),
+    };
+    let state = state.run();
+    if !state.is_finished() {
+        match state.checkpoint_path() {
+            Some(path) => println!(
+                "Training interrupted, resume it with --resume {}",
+                path.display()
+            ),
+            None => println!("Training interrupted"),
+        }
+        return Ok(());
+    }
+    let outputs = &experiment.outputs;
+    if let Some(path) = &outputs.metrics {
+        state.save_metrics(path)?;
+        println!("Saved metrics to {}", path.display());
+    }
+    state
+        .into_agent()
+        .save(&outputs.table)
+        .with_context(|| format!("Cannot save the Q-table to {}", outputs.table.display()))?;
+    println!("Saved Q-table to {}", outputs.table.display());
+    Ok(())
+}
+
+/// Trains, plays or evaluates a game learned by self-play, like ultimate tic-tac-toe or the cube
+fn run_self_play_game<G: Game>(
+    args: &Args,
+    experiment: &ExperimentConfig,
+    game: G,
+    gui: impl FnOnce(G, Option<QTable>),
+) -> anyhow::Result<()> {
+    let train = || -> anyhow::Result<QTable> {
+        let options = TrainingOptions {
+            checkpoint: experiment.outputs.checkpoint.clone(),
+            ..experiment.training_options()
+        };
+        let mut table = train_self_play(
+            game.clone(),
+            experiment.learner.n_games,
+            experiment.learner.n_epoch,
+            &options,
+        )?;
+        table.set_experiment(experiment);
+        Ok(table)
+    };
+    if args.train {
+        let table = train()?;
+        let path = &experiment.outputs.table;
+        table
+            .save(path)
+            .with_context(|| format!("Cannot save the Q-table to {}", path.display()))?;
+        println!("Saved Q-table to {}", path.display());
+    } else if args.gui {
+        let table = args.q_table.as_deref().map(QTable::load).transpose()?;
+        gui(game, table);
+    } else {
+        let table = match &args.q_table {
+            Some(path) => QTable::load(path)?,
+            None => train()?,
+        };
+        let result =
+            evaluation::evaluate_against_random(&table, game, args.n_games, &mut rng(experiment));
+        println!(
+            "Q-table vs random: score {}, win rate {}, draw rate {}, loss rate {}",
+            result.score(),
+            result.wins as f64 / result.n_games() as f64,
+            result.draws as f64 / result.n_games() as f64,
+            result.losses as f64 / result.n_games() as f64,
+        );
+    }
+    Ok(())
+}
+
+/// Loads the Q-table given with --q-table or by the experiment, or trains one
+fn load_or_train(
+    args: &Args,
+    experiment: &ExperimentConfig,
+    board: BoardConfig,
+) -> anyhow::Result<QTable> {
+    let saved = match &experiment.agent {
+        AgentConfig::SavedQTable { path } => Some(path),
+        _ => None,
+    };
+    Ok(match args.q_table.as_ref().or(saved) {
+        Some(path) => QTable::load(path)?,
+        None => train(
+            experiment.learner.n_games,
+            experiment.learner.n_epoch,
+            board,
+            &experiment.training_options(),
+        ),
+    })
+}
+
+/// Plans against a fixed opponent and compares the learned table to the plan
+fn run_plan(args: &Args, experiment: &ExperimentConfig, opponent: &str) -> anyhow::Result<()> {
+    let opponent = planning::OpponentModel::parse(opponent)?;
+    let plan = planning::value_iteration(
+        experiment.environment,
+        &opponent,
+        args.plan_gamma,
+        1e-9,
+        args.max_states,
+    )?;
+    println!(
+        "Value iteration converged in {} sweeps over {} positions",
+        plan.n_sweeps, plan.n_states
+    );
+    let output = &experiment.outputs.table;
+    plan.table.save(output)?;
+    println!("Saved Q-table to {}", output.display());
+    if let Some(path) = &args.q_table {
+        let comparison = planning::compare(&plan.table, &QTable::load(path)?);
+        println!(
+            "Learned table: mean absolute error {} over {} entries, optimal greedy move in {} of {} positions",
+            comparison.mean_absolute_error,
+            comparison.n_entries,
+            comparison.n_optimal,
+            comparison.n_states
+        );
+    }
+    Ok(())
+}
+
+fn run_hyperparameter_search(
+    args: &Args,
+    experiment: &ExperimentConfig,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let space: SearchSpace = load_json(path)?;
+    let config = SearchConfig {
+        strategy: match args.search_strategy {
+            SearchKind::Grid => Strategy::Grid,
+            SearchKind::Random => Strategy::Random {
+                n_trials: args.n_trials,
+            },
+        },
+        rounds: args.halving_rounds,
+        baseline: args.baseline,
+        eval_games: args.eval_games,
+        board: experiment.environment,
+        options: experiment.training_options(),
+        seed: experiment.seed.unwrap_or(0),
+    };
+    let summary = search::run_search(&space, &config)?;
+    print!("{}", summary);
+    save_json(&summary, &args.summary)?;
+    println!("Saved the search summary to {}", args.summary.display());
+    Ok(())
+}
+
+/// Prints what the Q-table thinks of a position
+fn run_inspect(args: &Args, experiment: &ExperimentConfig, position: &str) -> anyhow::Result<()> {
+    let board = Board::from_position(position, experiment.environment)?;
+    let table = load_or_train(args, experiment, board.config())?;
+    let stats = table.action_stats(&board);
+    print!(
+        "{}",
+        inspect::heatmap(&board, &stats, std::io::stdout().is_terminal())
+    );
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    if args.gui {
-        gui::TicTacToeApp::run(false);
+    let experiment = args.experiment()?;
+    let board = experiment.environment;
+    experiment.validate()?;
+    let start = experiment.start_board()?;
+    if let Some(mode) = args.empty_board_mode() {
+        anyhow::ensure!(
+            experiment.start.is_none() || args.save_config.is_some(),
+            "{} plays from the empty board, it can't start from a position",
+            mode
+        );
+    }
+    if let Some(path) = &args.save_config {
+        experiment.save(path)?;
+        println!("Saved the experiment config to {}", path.display());
+    } else if args.ultimate {
+        run_self_play_game(&args, &experiment, UltimateBoard::new(), |_, table| {
+            gui::TicTacToeApp::run_ultimate(table)
+        })?;
+    } else if args.cube {
+        let cube = Cube::from_config(board)?;
+        run_self_play_game(&args, &experiment, cube, gui::TicTacToeApp::run_cube)?;
+    } else if args.wild {
+        let wild = WildBoard::new(board);
+        run_self_play_game(&args, &experiment, wild, gui::TicTacToeApp::run_wild)?;
+    } else if let Some(n_boards) = args.notakto {
+        let notakto = Notakto::new(n_boards, board)?;
+        run_self_play_game(&args, &experiment, notakto, gui::TicTacToeApp::run_notakto)?;
+    } else if let Some(position) = &args.inspect {
+        run_inspect(&args, &experiment, position)?;
+    } else if let Some(opponent) = &args.plan {
+        run_plan(&args, &experiment, opponent)?;
+    } else if args.exploitability {
+        let table = load_or_train(&args, &experiment, board)?;
+        print!(
+            "{}",
+            exploitability::evaluate_exploitability(&table, board, args.max_states)?
+        );
+    } else if let Some(participants) = &args.tournament {
+        let tournament = tournament::run_tournament(
+            participants,
+            args.n_games,
+            board,
+            experiment.start.as_deref(),
+            &mut rng(&experiment),
+        )?;
+        print!("{}", tournament);
+        if let Some(path) = &args.export {
+            tournament.export(path)?;
+            println!("Exported results to {}", path.display());
+        }
+    } else if let Some(path) = &args.search {
+        run_hyperparameter_search(&args, &experiment, path)?;
+    } else if args.enumerate {
+        let enumeration = enumeration::enumerate(board, args.symmetry, args.max_states)
+            .with_context(|| format!("More than {} positions", args.max_states))?;
+        print!("{}", enumeration);
+    } else if args.stats {
+        let table = load_or_train(&args, &experiment, board)?;
+        if let Some(trained_by) = table.experiment() {
+            println!("Experiment: {}", serde_json::to_string(trained_by)?);
+        }
+        print!("{}", stats::TableStats::new(&table, board));
+    } else if args.train || args.resume.is_some() {
+        run_training(&args, &experiment)?;
+    } else if args.gui {
+        // Without a config file there's no opponent, both sides are played by hand
+        let opponent = args.config.as_ref().map(|_| experiment.agent_spec());
+        gui::TicTacToeApp::run(
+            opponent,
+            &experiment.environment,
+            experiment.start.as_deref(),
+        );
     } else if args.training {
-        gui::TicTacToeApp::run(true);
+        let opponent = args.agent_spec(AgentKind::QTable, &experiment);
+        gui::TicTacToeApp::run(
+            Some(opponent),
+            &experiment.environment,
+            experiment.start.as_deref(),
+        );
+    } else if args.mcts {
+        let opponent = args.agent_spec(AgentKind::Mcts, &experiment);
+        gui::TicTacToeApp::run(
+            Some(opponent),
+            &experiment.environment,
+            experiment.start.as_deref(),
+        );
+    } else if args.dqn {
+        let opponent = args.agent_spec(AgentKind::Dqn, &experiment);
+        gui::TicTacToeApp::run(
+            Some(opponent),
+            &experiment.environment,
+            experiment.start.as_deref(),
+        );
+    } else if args.evaluation {
+        let mut rng = rng(&experiment);
+        let mut agent = experiment.agent_spec().build(board, &mut rng)?;
+        let mut opponent = args
+            .agent_spec(args.opponent, &experiment)
+            .build(board, &mut rng)?;
+        let result = evaluation::evaluate(
+            agent.as_mut(),
+            opponent.as_mut(),
+            args.n_games,
+            &start,
+            &mut rng,
+        );
+        println!(
+            "{:?} vs {:?}: score {}, win rate {}, draw rate {}, loss rate {}",
+            experiment.agent,
+            args.opponent,
+            result.score(),
+            result.wins as f64 / result.n_games() as f64,
+            result.draws as f64 / result.n_games() as f64,
+            result.losses as f64 / result.n_games() as f64,
+        );
     }
+    Ok(())
 }
@@ -0,0 +1,234 @@
+use rand::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::{
+    agent::{Action, Agent, RandomAgent},
+    logic::{Board, CellState},
+};
+
+/// How much search the player is allowed per move
+#[derive(Clone, Copy, Debug)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+/// Policy used to finish a game from a freshly expanded node
+pub enum Rollout {
+    Random,
+    Policy(Box<dyn Agent>),
+}
+
+/// Node of the search tree, stored in an arena and linked by indices
+struct Node {
+    parent: Option<usize>,
+    /// Move leading to this node, `None` for the root
+    action: Option<Action>,
+    /// Player who played `action`, results are accumulated from their point of view
+    player: CellState,
+    children: Vec<usize>,
+    untried_actions: Vec<Action>,
+    visits: f64,
+    value: f64,
+}
+
+impl Node {
+    fn new(
+        parent: Option<usize>,
+        action: Option<Action>,
+        player: CellState,
+        board: &Board,
+    ) -> Self {
+        let untried_actions = if is_terminal(board) {
+            Vec::new()
+        } else {
+            Action::possible_actions(board)
+        };
+        Self {
+            parent,
+            action,
+            player,
+            children: Vec::new(),
+            untried_actions,
+            visits: 0.0,
+            value: 0.0,
+        }
+    }
+}
+
+fn is_terminal(board: &Board) -> bool {
+    board.is_winner().is_some() || board.is_board_full()
+}
+
+/// Monte Carlo Tree Search player using UCT selection
+pub struct Mcts {
+    exploration: f64,
+    budget: Budget,
+    rollout: Rollout,
+}
+
+impl Mcts {
+    pub fn new(exploration: f64, budget: Budget, rollout: Rollout) -> Self {
+        Self {
+            exploration,
+            budget,
+            rollout,
+        }
+    }
+
+    /// Runs the search from the given position and returns the most visited move
+    pub fn search(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        let mut tree = vec![Node::new(
+            None,
+            None,
+            board.get_current_player().opponent(),
+            board,
+        )];
+
+        let start = Instant::now();
+        let mut iteration = 0;
+        while match self.budget {
+            Budget::Iterations(n_iterations) => iteration < n_iterations,
+            Budget::Time(limit) => iteration == 0 || start.elapsed() < limit,
+        } {
+            let mut board = board.clone();
+            let mut node = 0;
+
+            // Selection
+            while tree[node].untried_actions.is_empty() && !tree[node].children.is_empty() {
+                node = self.select_child(&tree, node);
+                let action = tree[node].action.unwrap();
+                board.play_move(action.x_axis, action.y_axis);
+            }
+
+            // Expansion
+            if !tree[node].untried_actions.is_empty() {
+                let index = rng.gen_range(0..tree[node].untried_actions.len());
+                let action = tree[node].untried_actions.swap_remove(index);
+                let player = board.get_current_player();
+                board.play_move(action.x_axis, action.y_axis);
+                tree.push(Node::new(Some(node), Some(action), player, &board));
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation
+            let winner = self.simulate(&mut board, rng);
+
+            // Backpropagation
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let node = &mut tree[index];
+                node.visits += 1.0;
+                node.value += match winner {
+                    Some(winner) if winner == node.player => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                current = node.parent;
+            }
+            iteration += 1;
+        }
+
+        let best_child = tree[0]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| tree[a].visits.partial_cmp(&tree[b].visits).unwrap())
+            .expect("Cannot search a finished game");
+        tree[*best_child].action.unwrap()
+    }
+
+    /// Picks the child maximizing the UCT score
+    fn select_child(&self, tree: &[Node], node: usize) -> usize {
+        let log_visits = tree[node].visits.ln();
+        *tree[node]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| {
+                let score = |child: usize| {
+                    tree[child].value / tree[child].visits
+                        + self.exploration * (log_visits / tree[child].visits).sqrt()
+                };
+                score(a).partial_cmp(&score(b)).unwrap()
+            })
+            .unwrap()
+    }
+
+    /// Plays the game out with the rollout policy and returns the winner if there's any
+    fn simulate(&mut self, board: &mut Board, rng: &mut dyn RngCore) -> Option<CellState> {
+        loop {
+            if let Some(winner) = board.is_winner() {
+                return Some(winner);
+            }
+            if board.is_board_full() {
+                return None;
+            }
+            let action = match &mut self.rollout {
+                Rollout::Random => RandomAgent.select_action(board, rng),
+                Rollout::Policy(policy) => policy.select_action(board, rng),
+            };
+            board.play_move(action.x_axis, action.y_axis);
+        }
+    }
+}
+
+impl Agent for Mcts {
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        self.search(board, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::HeuristicAgent, logic::BoardConfig};
+    use rand_chacha::ChaCha8Rng;
+
+    fn board_from_moves(size: usize, win_condition: usize, moves: &[(usize, usize)]) -> Board {
+        let mut board = Board::new(BoardConfig::square(size, win_condition));
+        for &(x_axis, y_axis) in moves {
+            board.play_move(x_axis, y_axis);
+        }
+        board
+    }
+
+    #[test]
+    fn test_mcts_takes_win() {
+        // X to play and win on (0, 2)
+        let board = board_from_moves(3, 3, &[(0, 0), (1, 0), (0, 1), (1, 1)]);
+        let mut mcts = Mcts::new(1.4, Budget::Iterations(2000), Rollout::Random);
+        let action = mcts.search(&board, &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!((action.x_axis, action.y_axis), (0, 2));
+    }
+
+    #[test]
+    fn test_mcts_blocks_loss() {
+        // O has to block X on (0, 2)
+        let board = board_from_moves(3, 3, &[(0, 0), (1, 1), (0, 1)]);
+        let mut mcts = Mcts::new(1.4, Budget::Iterations(2000), Rollout::Random);
+        let action = mcts.search(&board, &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!((action.x_axis, action.y_axis), (0, 2));
+    }
+
+    #[test]
+    fn test_mcts_policy_rollouts() {
+        let board = board_from_moves(3, 3, &[(0, 0), (1, 1), (0, 1)]);
+        let rollout = Rollout::Policy(Box::new(HeuristicAgent));
+        let mut mcts = Mcts::new(1.4, Budget::Iterations(500), rollout);
+        let action = mcts.search(&board, &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!((action.x_axis, action.y_axis), (0, 2));
+    }
+
+    #[test]
+    fn test_mcts_time_budget() {
+        let board = Board::new(BoardConfig::square(7, 5));
+        let mut mcts = Mcts::new(
+            1.4,
+            Budget::Time(Duration::from_millis(50)),
+            Rollout::Random,
+        );
+        let action = mcts.search(&board, &mut ChaCha8Rng::seed_from_u64(0));
+        assert!(board.is_valid_move(action.x_axis, action.y_axis));
+    }
+}
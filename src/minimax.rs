@@ -0,0 +1,105 @@
+use rand::prelude::*;
+
+use crate::{
+    agent::{Action, Agent},
+    logic::Board,
+};
+
+/// Score of a win, reduced by the number of plies needed so faster wins are preferred
+const WIN_SCORE: i32 = 1000;
+
+/// Depth limited negamax search with alpha-beta pruning, positions at the depth limit are
+/// scored as draws
+pub struct MinimaxAgent {
+    depth: usize,
+}
+
+impl MinimaxAgent {
+    pub fn new(depth: usize) -> Self {
+        Self { depth }
+    }
+
+    /// Returns the score of the position for the player to move
+    fn negamax(&self, board: &Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if let Some(winner) = board.is_winner() {
+            // In the misère variant the player to move can win by the opponent's line
+            let score = WIN_SCORE - ply as i32;
+            return if winner == board.get_current_player() {
+                score
+            } else {
+                -score
+            };
+        }
+        if board.is_board_full() || ply >= self.depth {
+            return 0;
+        }
+        let mut best = -WIN_SCORE - 1;
+        for action in Action::possible_actions(board) {
+            let mut next_board = board.clone();
+            next_board.play_move(action.x_axis, action.y_axis);
+            let score = -self.negamax(&next_board, ply + 1, -beta, -alpha);
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Scores every legal move for the player to move
+    pub fn score_actions(&self, board: &Board) -> Vec<(Action, i32)> {
+        Action::possible_actions(board)
+            .into_iter()
+            .map(|action| {
+                let mut next_board = board.clone();
+                next_board.play_move(action.x_axis, action.y_axis);
+                let score = -self.negamax(&next_board, 1, -WIN_SCORE - 1, WIN_SCORE + 1);
+                (action, score)
+            })
+            .collect()
+    }
+}
+
+impl Agent for MinimaxAgent {
+    /// Plays one of the best scored moves, chosen at random
+    fn select_action(&mut self, board: &Board, rng: &mut dyn RngCore) -> Action {
+        let scores = self.score_actions(board);
+        let best_score = scores.iter().map(|&(_, score)| score).max().unwrap();
+        let best_actions: Vec<Action> = scores
+            .iter()
+            .filter(|&&(_, score)| score == best_score)
+            .map(|&(action, _)| action)
+            .collect();
+        *best_actions.choose(rng).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::RandomAgent, evaluation::evaluate, logic::BoardConfig};
+
+    #[test]
+    fn test_minimax_blocks_loss() {
+        let mut board = Board::new(BoardConfig::default());
+        for (x_axis, y_axis) in [(0, 0), (1, 1), (0, 1)] {
+            board.play_move(x_axis, y_axis);
+        }
+        let action = MinimaxAgent::new(9).select_action(&board, &mut rand::thread_rng());
+        assert_eq!((action.x_axis, action.y_axis), (0, 2));
+    }
+
+    #[test]
+    fn test_full_depth_minimax_never_loses() {
+        let mut rng = rand::thread_rng();
+        let result = evaluate(
+            &mut MinimaxAgent::new(9),
+            &mut RandomAgent,
+            20,
+            &Board::new(BoardConfig::default()),
+            &mut rng,
+        );
+        assert_eq!(result.losses, 0);
+    }
+}
@@ -0,0 +1,170 @@
+use ndarray::{Array1, Array2, Axis};
+use rand::prelude::*;
+
+const ADAM_BETA_1: f64 = 0.9;
+const ADAM_BETA_2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+/// Dense layer with its Adam moment estimates
+#[derive(Clone)]
+struct Layer {
+    weights: Array2<f64>,
+    biases: Array1<f64>,
+    m_weights: Array2<f64>,
+    v_weights: Array2<f64>,
+    m_biases: Array1<f64>,
+    v_biases: Array1<f64>,
+}
+
+impl Layer {
+    /// He initialization, suited to the ReLU activations of the hidden layers
+    fn new<R: Rng + ?Sized>(n_inputs: usize, n_outputs: usize, rng: &mut R) -> Self {
+        let scale = (2.0 / n_inputs as f64).sqrt();
+        Self {
+            weights: Array2::from_shape_fn((n_inputs, n_outputs), |_| {
+                (rng.gen::<f64>() * 2.0 - 1.0) * scale
+            }),
+            biases: Array1::zeros(n_outputs),
+            m_weights: Array2::zeros((n_inputs, n_outputs)),
+            v_weights: Array2::zeros((n_inputs, n_outputs)),
+            m_biases: Array1::zeros(n_outputs),
+            v_biases: Array1::zeros(n_outputs),
+        }
+    }
+}
+
+/// Multi layer perceptron with ReLU hidden layers and a linear output, trained with Adam on
+/// the mean squared error
+#[derive(Clone)]
+pub struct Mlp {
+    layers: Vec<Layer>,
+    n_updates: i32,
+}
+
+impl Mlp {
+    /// Creates a network, `layer_sizes` lists the input size, the hidden sizes, then the output
+    /// size
+    pub fn new<R: Rng + ?Sized>(layer_sizes: &[usize], rng: &mut R) -> Self {
+        Self {
+            layers: layer_sizes
+                .windows(2)
+                .map(|sizes| Layer::new(sizes[0], sizes[1], rng))
+                .collect(),
+            n_updates: 0,
+        }
+    }
+
+    /// Computes the outputs for a batch of inputs, one input per row
+    pub fn forward(&self, inputs: &Array2<f64>) -> Array2<f64> {
+        self.forward_activations(inputs).pop().unwrap()
+    }
+
+    /// Computes the outputs for a single input
+    pub fn predict(&self, input: &Array1<f64>) -> Array1<f64> {
+        let inputs = input.view().insert_axis(Axis(0)).to_owned();
+        self.forward(&inputs).row(0).to_owned()
+    }
+
+    /// Returns the activations of every layer, starting with the inputs
+    fn forward_activations(&self, inputs: &Array2<f64>) -> Vec<Array2<f64>> {
+        let mut activations = vec![inputs.clone()];
+        for (index, layer) in self.layers.iter().enumerate() {
+            let mut output = activations.last().unwrap().dot(&layer.weights) + &layer.biases;
+            if index < self.layers.len() - 1 {
+                output.mapv_inplace(|value| value.max(0.0));
+            }
+            activations.push(output);
+        }
+        activations
+    }
+
+    /// Performs one Adam step towards the targets and returns the loss before the step, each
+    /// row of the batch contributes to the loss proportionally to its weight
+    pub fn train_step(
+        &mut self,
+        inputs: &Array2<f64>,
+        targets: &Array2<f64>,
+        weights: &Array1<f64>,
+        learning_rate: f64,
+    ) -> f64 {
+        let activations = self.forward_activations(inputs);
+        let batch_size = inputs.nrows() as f64;
+        let row_weights = weights.view().insert_axis(Axis(1));
+        let errors = activations.last().unwrap() - targets;
+        let loss = (errors.mapv(|error| error * error) * row_weights).sum() / batch_size;
+
+        self.n_updates += 1;
+        let correction_1 = 1.0 - ADAM_BETA_1.powi(self.n_updates);
+        let correction_2 = 1.0 - ADAM_BETA_2.powi(self.n_updates);
+
+        let mut delta = errors * row_weights * (2.0 / batch_size);
+        for index in (0..self.layers.len()).rev() {
+            let grad_weights = activations[index].t().dot(&delta);
+            let grad_biases = delta.sum_axis(Axis(0));
+            if index > 0 {
+                let layer_inputs = &activations[index];
+                delta = delta.dot(&self.layers[index].weights.t());
+                delta.zip_mut_with(layer_inputs, |gradient, &activation| {
+                    if activation <= 0.0 {
+                        *gradient = 0.0;
+                    }
+                });
+            }
+
+            let layer = &mut self.layers[index];
+            layer.m_weights = &layer.m_weights * ADAM_BETA_1 + &grad_weights * (1.0 - ADAM_BETA_1);
+            layer.v_weights = &layer.v_weights * ADAM_BETA_2
+                + &grad_weights.mapv(|g| g * g) * (1.0 - ADAM_BETA_2);
+            layer.m_biases = &layer.m_biases * ADAM_BETA_1 + &grad_biases * (1.0 - ADAM_BETA_1);
+            layer.v_biases =
+                &layer.v_biases * ADAM_BETA_2 + &grad_biases.mapv(|g| g * g) * (1.0 - ADAM_BETA_2);
+
+            let step = |m: f64, v: f64| {
+                learning_rate * (m / correction_1) / ((v / correction_2).sqrt() + ADAM_EPSILON)
+            };
+            layer.weights.zip_mut_with(
+                &(ndarray::Zip::from(&layer.m_weights)
+                    .and(&layer.v_weights)
+                    .map_collect(|&m, &v| step(m, v))),
+                |weight, update| *weight -= update,
+            );
+            layer.biases.zip_mut_with(
+                &(ndarray::Zip::from(&layer.m_biases)
+                    .and(&layer.v_biases)
+                    .map_collect(|&m, &v| step(m, v))),
+                |bias, update| *bias -= update,
+            );
+        }
+        loss
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_mlp_output_shape() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mlp = Mlp::new(&[4, 8, 3], &mut rng);
+        assert_eq!(mlp.forward(&Array2::zeros((5, 4))).dim(), (5, 3));
+        assert_eq!(mlp.predict(&Array1::zeros(4)).len(), 3);
+    }
+
+    #[test]
+    fn test_mlp_learns_xor() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mlp = Mlp::new(&[2, 16, 1], &mut rng);
+        let inputs = array![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+        let targets = array![[0.0], [1.0], [1.0], [0.0]];
+        let weights = Array1::ones(4);
+        let initial_loss = mlp.train_step(&inputs, &targets, &weights, 0.01);
+        let mut loss = initial_loss;
+        for _ in 0..2000 {
+            loss = mlp.train_step(&inputs, &targets, &weights, 0.01);
+        }
+        assert!(loss < 0.01, "loss {} from {}", loss, initial_loss);
+    }
+}
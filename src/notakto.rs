@@ -0,0 +1,202 @@
+use anyhow::ensure;
+
+use crate::{
+    agent::{Action, Game, State},
+    logic::{Board, BoardConfig, CellState, Variant},
+};
+
+/// Player of an impartial game, where both players have the same moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Player {
+    First,
+    Second,
+}
+
+impl Player {
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::First => Player::Second,
+            Player::Second => Player::First,
+        }
+    }
+
+    /// Side of the player for the learners, which tell players apart by `CellState`
+    pub fn side(self) -> CellState {
+        match self {
+            Player::First => CellState::X,
+            Player::Second => CellState::O,
+        }
+    }
+}
+
+/// Notakto: both players place X on any board still alive, a board with a line is dead, and
+/// whoever kills the last board loses. Actions pick the board with their layer.
+#[derive(Clone, Debug)]
+pub struct Notakto {
+    boards: Vec<Board>,
+    turn: Player,
+    /// Set once every board is dead
+    winner: Option<Player>,
+}
+
+impl Notakto {
+    pub fn new(n_boards: usize, config: BoardConfig) -> anyhow::Result<Self> {
+        ensure!(n_boards > 0, "Notakto needs at least one board");
+        config.validate()?;
+        let config = BoardConfig {
+            variant: Variant::Standard,
+            ..config
+        };
+        Ok(Self {
+            boards: vec![Board::new(config); n_boards],
+            turn: Player::First,
+            winner: None,
+        })
+    }
+
+    pub fn n_boards(&self) -> usize {
+        self.boards.len()
+    }
+
+    pub fn config(&self) -> BoardConfig {
+        self.boards[0].config()
+    }
+
+    pub fn reset(&mut self) {
+        for board in &mut self.boards {
+            board.reset();
+        }
+        self.turn = Player::First;
+        self.winner = None;
+    }
+
+    /// Returns whether the board holds a line, no move can be played on it anymore
+    pub fn is_dead(&self, index: usize) -> bool {
+        self.boards[index].is_winner().is_some()
+    }
+
+    pub fn is_valid_move(&self, x_axis: usize, y_axis: usize, index: usize) -> bool {
+        index < self.boards.len()
+            && !self.is_dead(index)
+            && self.boards[index].is_valid_move(x_axis, y_axis)
+    }
+
+    /// Places an X for the player to move, killing the last board loses
+    pub fn play_move(&mut self, x_axis: usize, y_axis: usize, index: usize) {
+        if !self.is_valid_move(x_axis, y_axis, index) {
+            return;
+        }
+        let board = &mut self.boards[index];
+        board.set_current_player(CellState::X);
+        board.play_move(x_axis, y_axis);
+        if (0..self.boards.len()).all(|index| self.is_dead(index)) {
+            self.winner = Some(self.turn.opponent());
+        }
+        self.turn = self.turn.opponent();
+    }
+
+    pub fn get_current_player(&self) -> Player {
+        self.turn
+    }
+
+    pub fn is_winner(&self) -> Option<Player> {
+        self.winner
+    }
+
+    /// Returns whether no move is left, which only happens once every board is dead, unless
+    /// boards fill up without a line
+    pub fn is_over(&self) -> bool {
+        self.get_possible_actions().is_empty()
+    }
+
+    pub fn get_possible_actions(&self) -> Vec<Action> {
+        let mut possible_actions = Vec::new();
+        for (index, board) in self.boards.iter().enumerate() {
+            if self.is_dead(index) {
+                continue;
+            }
+            for (x_axis, y_axis) in board.get_possible_actions() {
+                possible_actions.push(Action {
+                    x_axis,
+                    y_axis,
+                    layer: index,
+                    symbol: None,
+                });
+            }
+        }
+        possible_actions
+    }
+
+    /// Grids of the boards side by side along the x axis
+    pub fn get_grid(&self) -> State {
+        self.boards.iter().flat_map(Board::get_grid).collect()
+    }
+}
+
+impl Game for Notakto {
+    fn reset(&mut self) {
+        Notakto::reset(self);
+    }
+
+    fn play(&mut self, action: Action) {
+        self.play_move(action.x_axis, action.y_axis, action.layer);
+    }
+
+    fn winner(&self) -> Option<CellState> {
+        self.is_winner().map(Player::side)
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_over()
+    }
+
+    fn possible_actions(&self) -> Vec<Action> {
+        self.get_possible_actions()
+    }
+
+    fn state(&self) -> State {
+        self.get_grid()
+    }
+
+    fn current_player(&self) -> CellState {
+        self.get_current_player().side()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_killing_the_last_board_loses() {
+        let mut notakto = Notakto::new(2, BoardConfig::default()).unwrap();
+        assert_eq!(notakto.get_possible_actions().len(), 18);
+        // The first player kills the first board, the game goes on
+        for (x_axis, y_axis) in [(0, 0), (1, 0), (2, 0)] {
+            notakto.play_move(x_axis, y_axis, 0);
+        }
+        assert!(notakto.is_dead(0));
+        assert_eq!(notakto.is_winner(), None);
+        assert_eq!(notakto.get_possible_actions().len(), 9);
+        assert!(!notakto.is_valid_move(1, 1, 0));
+
+        // The second player kills the last board
+        for (x_axis, y_axis) in [(0, 0), (1, 1), (2, 2)] {
+            notakto.play_move(x_axis, y_axis, 1);
+        }
+        assert_eq!(notakto.is_winner(), Some(Player::First));
+        assert!(notakto.is_over());
+    }
+
+    #[test]
+    fn test_every_mark_is_an_x() {
+        let mut notakto = Notakto::new(1, BoardConfig::default()).unwrap();
+        notakto.play_move(0, 0, 0);
+        notakto.play_move(1, 1, 0);
+        assert_eq!(notakto.get_current_player(), Player::First);
+        let grid = notakto.get_grid();
+        assert_eq!(grid[0][0], CellState::X);
+        assert_eq!(grid[1][1], CellState::X);
+        assert!(Notakto::new(0, BoardConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use anyhow::{bail, Context};
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    agent::{Action, Agent, QTable, Reward, State},
+    enumeration::enumerate,
+    logic::{Board, BoardConfig, CellState},
+    minimax::MinimaxAgent,
+};
+
+/// Fixed policy of the opponent the plan is computed against
+pub enum OpponentModel {
+    Random,
+    /// Plays uniformly among the best moves of the search
+    Minimax(MinimaxAgent),
+    /// Plays uniformly among the greedy moves of the table
+    Table(QTable),
+}
+
+impl OpponentModel {
+    /// Parses `random`, `minimax:<depth>` or `table:<path>`, loading the table
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        Ok(match spec.split_once(':') {
+            None if spec == "random" => OpponentModel::Random,
+            Some(("minimax", depth)) => OpponentModel::Minimax(MinimaxAgent::new(
+                depth
+                    .parse()
+                    .with_context(|| format!("Invalid depth in opponent `{}`", spec))?,
+            )),
+            Some(("table", path)) => OpponentModel::Table(QTable::load(Path::new(path))?),
+            _ => bail!("Unknown opponent `{}`", spec),
+        })
+    }
+
+    /// Probability of every legal move of the player to move
+    fn distribution(&self, board: &Board) -> Vec<(Action, f64)> {
+        let scored: Vec<(Action, f64)> = match self {
+            OpponentModel::Random => {
+                let actions = Action::possible_actions(board);
+                let probability = 1.0 / actions.len() as f64;
+                return actions
+                    .into_iter()
+                    .map(|action| (action, probability))
+                    .collect();
+            }
+            OpponentModel::Minimax(agent) => agent
+                .score_actions(board)
+                .into_iter()
+                .map(|(action, score)| (action, score as f64))
+                .collect(),
+            OpponentModel::Table(table) => table.action_values(board).unwrap(),
+        };
+        let best = scored
+            .iter()
+            .map(|&(_, score)| score)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let best_actions: Vec<Action> = scored
+            .into_iter()
+            .filter(|&(_, score)| score == best)
+            .map(|(action, _)| action)
+            .collect();
+        let probability = 1.0 / best_actions.len() as f64;
+        best_actions
+            .into_iter()
+            .map(|action| (action, probability))
+            .collect()
+    }
+}
+
+/// Where a move of the learner and the reply of the opponent lead
+enum Successor {
+    Terminal(f64),
+    State(usize),
+}
+
+/// Moves of a position, each with its successors and their probabilities
+type Moves = Vec<(Action, Vec<(f64, Successor)>)>;
+
+/// Result of a value iteration
+pub struct Plan {
+    /// Optimal Q-values of the learner, in the format of the Q-learning tables
+    pub table: QTable,
+    pub n_states: usize,
+    pub n_sweeps: usize,
+}
+
+/// Computes the optimal Q-values of the player to move in every reachable position against
+/// the fixed opponent, by sweeping over all the positions until no value changes by more than
+/// `tolerance`. Rewards and discount follow the Q-learning training, which also sees a position
+/// of the learner once the opponent replied.
+pub fn value_iteration(
+    config: BoardConfig,
+    opponent: &OpponentModel,
+    gamma: f64,
+    tolerance: f64,
+    max_states: usize,
+) -> anyhow::Result<Plan> {
+    let states: Vec<State> = enumerate(config, false, max_states)
+        .with_context(|| format!("More than {} positions to plan over", max_states))?
+        .states
+        .into_iter()
+        .collect();
+    let index: HashMap<&State, usize> = states
+        .iter()
+        .enumerate()
+        .map(|(index, state)| (state, index))
+        .collect();
+
+    // Outcomes of every move of every position, with their probabilities
+    let transitions: Vec<Moves> = states
+        .iter()
+        .map(|state| {
+            let board = Board::from_grid(state.clone(), config);
+            Action::possible_actions(&board)
+                .into_iter()
+                .map(|action| {
+                    let mut after_move = board.clone();
+                    after_move.play_move(action.x_axis, action.y_axis);
+                    let player = board.get_current_player();
+                    let outcome = |winner: CellState| {
+                        if winner == player {
+                            Reward::WIN as f64
+                        } else {
+                            Reward::LOSS as f64
+                        }
+                    };
+                    if let Some(winner) = after_move.is_winner() {
+                        return (action, vec![(1.0, Successor::Terminal(outcome(winner)))]);
+                    }
+                    if after_move.is_board_full() {
+                        return (
+                            action,
+                            vec![(1.0, Successor::Terminal(Reward::DRAW as f64))],
+                        );
+                    }
+                    let successors = opponent
+                        .distribution(&after_move)
+                        .into_iter()
+                        .map(|(reply, probability)| {
+                            let mut after_reply = after_move.clone();
+                            after_reply.play_move(reply.x_axis, reply.y_axis);
+                            let successor = if let Some(winner) = after_reply.is_winner() {
+                                Successor::Terminal(outcome(winner))
+                            } else if after_reply.is_board_full() {
+                                Successor::Terminal(Reward::DRAW as f64)
+                            } else {
+                                Successor::State(index[&after_reply.get_grid()])
+                            };
+                            (probability, successor)
+                        })
+                        .collect();
+                    (action, successors)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut q_values: Vec<Vec<f64>> = transitions
+        .iter()
+        .map(|moves| vec![0.0; moves.len()])
+        .collect();
+    let mut n_sweeps = 0;
+    loop {
+        let values: Vec<f64> = q_values
+            .iter()
+            .map(|q| q.iter().cloned().fold(f64::NEG_INFINITY, f64::max))
+            .collect();
+        let mut max_change: f64 = 0.0;
+        for (moves, q) in transitions.iter().zip(q_values.iter_mut()) {
+            for ((_, successors), q) in moves.iter().zip(q.iter_mut()) {
+                let target: f64 = successors
+                    .iter()
+                    .map(|(probability, successor)| {
+                        probability
+                            * match *successor {
+                                Successor::Terminal(reward) => reward,
+                                Successor::State(next) => gamma * values[next],
+                            }
+                    })
+                    .sum();
+                max_change = max_change.max((target - *q).abs());
+                *q = target;
+            }
+        }
+        n_sweeps += 1;
+        if max_change < tolerance {
+            break;
+        }
+    }
+
+    let table = QTable::from_values(
+        states
+            .iter()
+            .zip(transitions.iter().zip(q_values.iter()))
+            .flat_map(|(state, (moves, q))| {
+                moves
+                    .iter()
+                    .zip(q.iter())
+                    .map(move |((action, _), &q)| (state.clone(), *action, q))
+            }),
+        gamma,
+    );
+    Ok(Plan {
+        table,
+        n_states: states.len(),
+        n_sweeps,
+    })
+}
+
+/// How far a learned table is from the optimal one
+#[derive(Debug)]
+pub struct Comparison {
+    /// Entries of the learned table also in the plan
+    pub n_entries: usize,
+    pub mean_absolute_error: f64,
+    /// Positions of the learned table also in the plan
+    pub n_states: usize,
+    /// Positions where the greedy move of the learned table is optimal
+    pub n_optimal: usize,
+}
+
+/// Compares the values and greedy moves of a learned table to the planned ones
+pub fn compare(plan: &QTable, learned: &QTable) -> Comparison {
+    let mut optimal: HashMap<&State, Vec<(Action, f64)>> = HashMap::new();
+    for (state, action, entry) in plan.entries() {
+        optimal.entry(state).or_default().push((action, entry.q));
+    }
+
+    let mut n_entries = 0;
+    let mut total_error = 0.0;
+    for (state, action, entry) in learned.entries() {
+        let planned = optimal
+            .get(state)
+            .and_then(|moves| moves.iter().find(|&&(a, _)| a == action));
+        if let Some(&(_, q)) = planned {
+            n_entries += 1;
+            total_error += (entry.q - q).abs();
+        }
+    }
+
+    let mut n_states = 0;
+    let mut n_optimal = 0;
+    for (state, action) in learned.greedy_actions() {
+        if let Some(moves) = optimal.get(&state) {
+            n_states += 1;
+            let best = moves
+                .iter()
+                .map(|&(_, q)| q)
+                .fold(f64::NEG_INFINITY, f64::max);
+            if moves
+                .iter()
+                .any(|&(a, q)| a == action && (best - q).abs() < 1e-9)
+            {
+                n_optimal += 1;
+            }
+        }
+    }
+
+    Comparison {
+        n_entries,
+        mean_absolute_error: total_error / n_entries.max(1) as f64,
+        n_states,
+        n_optimal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_first_move(plan: &Plan) -> f64 {
+        let board = Board::new(BoardConfig::default());
+        plan.table
+            .action_values(&board)
+            .unwrap()
+            .into_iter()
+            .map(|(_, q)| q)
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    #[test]
+    fn test_perfect_opponent_forces_a_draw() {
+        let opponent = OpponentModel::Minimax(MinimaxAgent::new(9));
+        let plan =
+            value_iteration(BoardConfig::default(), &opponent, 1.0, 1e-9, usize::MAX).unwrap();
+        assert_eq!(plan.n_states, 4520);
+        assert!((best_first_move(&plan) - Reward::DRAW as f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_random_opponent_is_exploited() {
+        let plan = value_iteration(
+            BoardConfig::default(),
+            &OpponentModel::Random,
+            1.0,
+            1e-9,
+            usize::MAX,
+        )
+        .unwrap();
+        assert!(best_first_move(&plan) > 1.9);
+        let comparison = compare(&plan.table, &plan.table);
+        assert_eq!(comparison.mean_absolute_error, 0.0);
+        assert_eq!(comparison.n_optimal, comparison.n_states);
+    }
+}
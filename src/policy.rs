@@ -0,0 +1,165 @@
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Rule turning the Q-values of the legal actions into a choice
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Policy {
+    /// Always the best action, used for evaluation and play
+    Greedy,
+    EpsilonGreedy {
+        epsilon: f64,
+    },
+    /// Samples actions with probabilities proportional to `exp(q / temperature)`
+    Boltzmann {
+        temperature: f64,
+    },
+    /// Adds a bonus to rarely tried actions, `exploration * sqrt(ln(N + 1) / (n + 1))`
+    Ucb {
+        exploration: f64,
+    },
+}
+
+impl Policy {
+    /// Returns the index of the chosen action, `visits` are only used by the UCB policy
+    pub fn select<R: Rng + ?Sized>(
+        &self,
+        q_values: &[f64],
+        visits: &[usize],
+        rng: &mut R,
+    ) -> usize {
+        match *self {
+            Policy::Greedy => argmax_random_tie(q_values, rng),
+            Policy::EpsilonGreedy { epsilon } => {
+                if rng.gen::<f64>() < epsilon {
+                    rng.gen_range(0..q_values.len())
+                } else {
+                    argmax_random_tie(q_values, rng)
+                }
+            }
+            Policy::Boltzmann { temperature } => {
+                let max_q = q_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                // Subtracting the max keeps the exponentials finite at low temperatures
+                let weights: Vec<f64> = q_values
+                    .iter()
+                    .map(|q| ((q - max_q) / temperature.max(f64::EPSILON)).exp())
+                    .collect();
+                let total: f64 = weights.iter().sum();
+                let mut threshold = rng.gen::<f64>() * total;
+                for (index, weight) in weights.iter().enumerate() {
+                    threshold -= weight;
+                    if threshold <= 0.0 {
+                        return index;
+                    }
+                }
+                weights.len() - 1
+            }
+            Policy::Ucb { exploration } => {
+                let total_visits: usize = visits.iter().sum();
+                let log_total = ((total_visits + 1) as f64).ln();
+                let scores: Vec<f64> = q_values
+                    .iter()
+                    .zip(visits)
+                    .map(|(q, &n)| q + exploration * (log_total / (n + 1) as f64).sqrt())
+                    .collect();
+                argmax_random_tie(&scores, rng)
+            }
+        }
+    }
+}
+
+/// Index of the highest value, ties are broken uniformly at random
+pub fn argmax_random_tie<R: Rng + ?Sized>(values: &[f64], rng: &mut R) -> usize {
+    let max_value = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let best: Vec<usize> = (0..values.len())
+        .filter(|&index| values[index] == max_value)
+        .collect();
+    *best.choose(rng).unwrap()
+}
+
+/// Exploration strategy of a training run, resolved into a policy at every epoch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Exploration {
+    /// Follows the epsilon of the hyperparameter schedule
+    #[default]
+    EpsilonGreedy,
+    /// Temperature decaying geometrically from the initial to the final value
+    Boltzmann {
+        initial_temperature: f64,
+        final_temperature: f64,
+    },
+    Ucb {
+        exploration: f64,
+    },
+}
+
+impl Exploration {
+    /// Policy to follow once `progress` (between 0 and 1) of the training is done
+    pub fn policy(&self, progress: f64, epsilon: f64) -> Policy {
+        match *self {
+            Exploration::EpsilonGreedy => Policy::EpsilonGreedy { epsilon },
+            Exploration::Boltzmann {
+                initial_temperature,
+                final_temperature,
+            } => Policy::Boltzmann {
+                temperature: initial_temperature
+                    * (final_temperature / initial_temperature).powf(progress),
+            },
+            Exploration::Ucb { exploration } => Policy::Ucb { exploration },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_greedy_breaks_ties_randomly() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let q_values = [1.0, 0.0, 1.0, 1.0];
+        let mut chosen = [0; 4];
+        for _ in 0..300 {
+            chosen[Policy::Greedy.select(&q_values, &[0; 4], &mut rng)] += 1;
+        }
+        assert_eq!(chosen[1], 0);
+        assert!(chosen[0] > 50 && chosen[2] > 50 && chosen[3] > 50);
+    }
+
+    #[test]
+    fn test_boltzmann_temperature() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let q_values = [1.0, 0.0];
+        let cold = Policy::Boltzmann { temperature: 0.01 };
+        assert!((0..100).all(|_| cold.select(&q_values, &[0; 2], &mut rng) == 0));
+        let hot = Policy::Boltzmann { temperature: 100.0 };
+        let n_second = (0..1000)
+            .filter(|_| hot.select(&q_values, &[0; 2], &mut rng) == 1)
+            .count();
+        assert!(n_second > 400);
+    }
+
+    #[test]
+    fn test_ucb_prefers_unvisited_actions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let policy = Policy::Ucb { exploration: 2.0 };
+        assert_eq!(policy.select(&[0.5, 0.4], &[100, 0], &mut rng), 1);
+        assert_eq!(policy.select(&[0.5, 0.4], &[100, 100], &mut rng), 0);
+    }
+
+    #[test]
+    fn test_boltzmann_schedule_decays() {
+        let exploration = Exploration::Boltzmann {
+            initial_temperature: 1.0,
+            final_temperature: 0.01,
+        };
+        assert_eq!(
+            exploration.policy(0.0, 0.5),
+            Policy::Boltzmann { temperature: 1.0 }
+        );
+        let Policy::Boltzmann { temperature } = exploration.policy(1.0, 0.5) else {
+            panic!("Expected a Boltzmann policy");
+        };
+        assert!((temperature - 0.01).abs() < 1e-12);
+    }
+}
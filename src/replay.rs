@@ -0,0 +1,197 @@
+use anyhow::ensure;
+use rand::{distributions::WeightedIndex, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{Action, State};
+
+/// Offset keeping transitions with a null TD error sampleable
+const PRIORITY_OFFSET: f64 = 1e-3;
+
+/// Transition stored for off-policy tabular learning
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub state: State,
+    pub action: Action,
+    pub reward: f64,
+    pub next_state: State,
+    pub next_actions: Vec<Action>,
+    pub done: bool,
+}
+
+/// How transitions are drawn from the buffer
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Sampling {
+    Uniform,
+    /// Draws transitions proportionally to `|td_error|^alpha`, and corrects the bias with
+    /// importance sampling weights raised to `beta`
+    Prioritized {
+        alpha: f64,
+        beta: f64,
+    },
+}
+
+/// Settings of a replay buffer and of the minibatches drawn from it
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    pub capacity: usize,
+    pub batch_size: usize,
+    pub sampling: Sampling,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            batch_size: 32,
+            sampling: Sampling::Uniform,
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// Checks that the buffer can hold at least one transition
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.capacity > 0,
+            "The replay capacity must be at least 1, it holds the transitions to replay"
+        );
+        Ok(())
+    }
+}
+
+/// Minibatch drawn from the buffer, the weights have to scale the updates of each transition
+pub struct Batch {
+    pub indices: Vec<usize>,
+    pub weights: Vec<f64>,
+}
+
+/// Fixed capacity ring of transitions, overwriting the oldest ones once full
+#[derive(Serialize, Deserialize)]
+pub struct ReplayBuffer<T> {
+    transitions: Vec<T>,
+    priorities: Vec<f64>,
+    capacity: usize,
+    position: usize,
+    max_priority: f64,
+    sampling: Sampling,
+}
+
+impl<T> ReplayBuffer<T> {
+    /// Creates an empty buffer, the capacity has to be positive, see `ReplayConfig::validate`
+    pub fn new(capacity: usize, sampling: Sampling) -> Self {
+        assert!(capacity > 0, "A replay buffer needs a positive capacity");
+        Self {
+            transitions: Vec::with_capacity(capacity),
+            priorities: Vec::with_capacity(capacity),
+            capacity,
+            position: 0,
+            max_priority: 1.0,
+            sampling,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Stores a transition with the highest priority seen so far, so it gets replayed at least
+    /// once
+    pub fn push(&mut self, transition: T) {
+        if self.transitions.len() < self.capacity {
+            self.transitions.push(transition);
+            self.priorities.push(self.max_priority);
+        } else {
+            self.transitions[self.position] = transition;
+            self.priorities[self.position] = self.max_priority;
+        }
+        self.position = (self.position + 1) % self.capacity;
+    }
+
+    pub fn get(&self, index: usize) -> &T {
+        &self.transitions[index]
+    }
+
+    /// Draws a minibatch, returns `None` until the buffer holds enough transitions
+    pub fn sample<R: Rng + ?Sized>(&self, batch_size: usize, rng: &mut R) -> Option<Batch> {
+        if self.len() < batch_size || batch_size == 0 {
+            return None;
+        }
+        match self.sampling {
+            Sampling::Uniform => Some(Batch {
+                indices: rand::seq::index::sample(rng, self.len(), batch_size).into_vec(),
+                weights: vec![1.0; batch_size],
+            }),
+            Sampling::Prioritized { alpha, beta } => {
+                let scaled: Vec<f64> = self.priorities.iter().map(|p| p.powf(alpha)).collect();
+                let total: f64 = scaled.iter().sum();
+                let distribution = WeightedIndex::new(&scaled).ok()?;
+                let indices: Vec<usize> =
+                    (0..batch_size).map(|_| distribution.sample(rng)).collect();
+                let weights: Vec<f64> = indices
+                    .iter()
+                    .map(|&index| (self.len() as f64 * scaled[index] / total).powf(-beta))
+                    .collect();
+                let max_weight = weights.iter().cloned().fold(f64::MIN_POSITIVE, f64::max);
+                Some(Batch {
+                    indices,
+                    weights: weights.iter().map(|weight| weight / max_weight).collect(),
+                })
+            }
+        }
+    }
+
+    /// Records the latest TD error of a replayed transition
+    pub fn update_priority(&mut self, index: usize, td_error: f64) {
+        let priority = td_error.abs() + PRIORITY_OFFSET;
+        self.priorities[index] = priority;
+        self.max_priority = self.max_priority.max(priority);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_ring_overwrites_oldest() {
+        let mut buffer = ReplayBuffer::new(3, Sampling::Uniform);
+        for value in 0..5 {
+            buffer.push(value);
+        }
+        assert_eq!(buffer.len(), 3);
+        let mut values: Vec<i32> = (0..3).map(|index| *buffer.get(index)).collect();
+        values.sort();
+        assert_eq!(values, vec![2, 3, 4]);
+
+        let config = ReplayConfig {
+            capacity: 0,
+            ..ReplayConfig::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(ReplayConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_prioritized_sampling_favours_large_errors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sampling = Sampling::Prioritized {
+            alpha: 1.0,
+            beta: 0.4,
+        };
+        let mut buffer = ReplayBuffer::new(10, sampling);
+        for value in 0..10 {
+            buffer.push(value);
+        }
+        for index in 0..10 {
+            buffer.update_priority(index, if index == 7 { 100.0 } else { 0.0 });
+        }
+        let batch = buffer.sample(10, &mut rng).unwrap();
+        let n_sevens = batch.indices.iter().filter(|&&index| index == 7).count();
+        assert!(n_sevens > 8);
+        assert!(batch.weights.iter().all(|&weight| weight <= 1.0));
+        assert!(ReplayBuffer::<i32>::new(10, Sampling::Uniform)
+            .sample(1, &mut rng)
+            .is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use anyhow::ensure;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Instant};
+
+use crate::{
+    agent::{train, Rewards, Schedule, TrainingOptions},
+    evaluation::evaluate,
+    league::OpponentKind,
+    logic::{Board, BoardConfig},
+    policy::Policy,
+};
+
+/// Candidate values of every searched setting, a trial picks one value of each
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSpace {
+    /// Grids of the alpha schedule
+    pub alpha: Vec<Vec<f64>>,
+    pub gamma: Vec<Vec<f64>>,
+    pub epsilon: Vec<Vec<f64>>,
+    pub rewards: Vec<Rewards>,
+    pub n_epoch: Vec<usize>,
+    /// Games per epoch
+    pub n_games: Vec<usize>,
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        let schedule = Schedule::default();
+        Self {
+            alpha: vec![schedule.alpha],
+            gamma: vec![schedule.gamma],
+            epsilon: vec![schedule.epsilon],
+            rewards: vec![Rewards::default()],
+            n_epoch: vec![200],
+            n_games: vec![1000],
+        }
+    }
+}
+
+/// Settings of a trial
+#[derive(Clone, Debug, Serialize)]
+pub struct TrialConfig {
+    pub schedule: Schedule,
+    pub rewards: Rewards,
+    pub n_epoch: usize,
+    pub n_games: usize,
+}
+
+impl SearchSpace {
+    fn dimensions(&self) -> [usize; 6] {
+        [
+            self.alpha.len(),
+            self.gamma.len(),
+            self.epsilon.len(),
+            self.rewards.len(),
+            self.n_epoch.len(),
+            self.n_games.len(),
+        ]
+    }
+
+    fn config(&self, indices: [usize; 6]) -> TrialConfig {
+        TrialConfig {
+            schedule: Schedule {
+                alpha: self.alpha[indices[0]].clone(),
+                gamma: self.gamma[indices[1]].clone(),
+                epsilon: self.epsilon[indices[2]].clone(),
+            },
+            rewards: self.rewards[indices[3]],
+            n_epoch: self.n_epoch[indices[4]],
+            n_games: self.n_games[indices[5]],
+        }
+    }
+
+    /// Every combination of the candidate values
+    fn grid(&self) -> Vec<TrialConfig> {
+        let dimensions = self.dimensions();
+        let n_configs: usize = dimensions.iter().product();
+        (0..n_configs)
+            .map(|mut index| {
+                let mut indices = [0; 6];
+                for (i, &size) in dimensions.iter().enumerate() {
+                    indices[i] = index % size;
+                    index /= size;
+                }
+                self.config(indices)
+            })
+            .collect()
+    }
+
+    /// Combinations drawn at random, each value uniformly among its candidates
+    fn random(&self, n_trials: usize, rng: &mut ChaCha8Rng) -> Vec<TrialConfig> {
+        let dimensions = self.dimensions();
+        (0..n_trials)
+            .map(|_| self.config(dimensions.map(|size| rng.gen_range(0..size))))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Strategy {
+    Grid,
+    Random { n_trials: usize },
+}
+
+/// How trials are run and scored
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    pub strategy: Strategy,
+    /// Number of successive halving rounds, every round trains the best half of the trials of
+    /// the previous one with twice the epochs, the last one with the full budget. 1 trains every
+    /// trial once with its full budget.
+    pub rounds: usize,
+    /// Opponent of the evaluation games the trials are ranked by
+    pub baseline: OpponentKind,
+    pub eval_games: usize,
+    pub board: BoardConfig,
+    /// Options shared by every trial, the searched ones are overridden
+    pub options: TrainingOptions,
+    pub seed: u64,
+}
+
+/// Score of a trial in its last round
+#[derive(Clone, Debug, Serialize)]
+pub struct TrialResult {
+    pub trial: usize,
+    pub config: TrialConfig,
+    /// Last successive halving round the trial reached, from 0
+    pub round: usize,
+    /// Epochs of the training of that round
+    pub n_epoch: usize,
+    pub score: f64,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub seconds: f64,
+}
+
+/// Results of a search, best trials first
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchSummary {
+    pub baseline: String,
+    pub eval_games: usize,
+    pub results: Vec<TrialResult>,
+}
+
+/// Trains and evaluates a trial with the given number of epochs, seeded by its index so
+/// results don't depend on scheduling
+fn run_trial(
+    trial: usize,
+    config: &TrialConfig,
+    round: usize,
+    n_epoch: usize,
+    search: &SearchConfig,
+) -> TrialResult {
+    let start = Instant::now();
+    let options = TrainingOptions {
+        schedule: config.schedule.clone(),
+        rewards: config.rewards,
+        seed: Some(search.seed.wrapping_add(trial as u64)),
+        quiet: true,
+        ..search.options.clone()
+    };
+    let mut table = train(config.n_games, n_epoch, search.board, &options);
+    table.set_policy(Policy::Greedy);
+    let mut opponent = search
+        .baseline
+        .build()
+        .expect("Snapshots can't be evaluation opponents");
+    let mut rng = ChaCha8Rng::seed_from_u64(search.seed.wrapping_add(trial as u64));
+    let result = evaluate(
+        &mut table,
+        opponent.as_mut(),
+        search.eval_games,
+        &Board::from_start(search.options.start.as_deref(), search.board)
+            .expect("The start position was checked with the experiment"),
+        &mut rng,
+    );
+    TrialResult {
+        trial,
+        config: config.clone(),
+        round,
+        n_epoch,
+        score: result.score(),
+        wins: result.wins,
+        draws: result.draws,
+        losses: result.losses,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
+
+/// Runs the trials of the search in parallel and ranks them by score against the baseline
+pub fn run_search(space: &SearchSpace, search: &SearchConfig) -> anyhow::Result<SearchSummary> {
+    ensure!(
+        space.dimensions().iter().all(|&size| size > 0),
+        "Every setting of the search space needs at least one value"
+    );
+    for (name, grids) in [
+        ("alpha", &space.alpha),
+        ("gamma", &space.gamma),
+        ("epsilon", &space.epsilon),
+    ] {
+        ensure!(
+            grids.iter().all(|grid| !grid.is_empty()),
+            "Every {} schedule of the search space needs at least one value",
+            name
+        );
+    }
+    ensure!(
+        search.rounds < usize::BITS as usize,
+        "At most {} halving rounds, the first one divides the epochs by 2 for every later round",
+        usize::BITS - 1
+    );
+    let mut rng = ChaCha8Rng::seed_from_u64(search.seed);
+    let configs = match search.strategy {
+        Strategy::Grid => space.grid(),
+        Strategy::Random { n_trials } => space.random(n_trials, &mut rng),
+    };
+    println!("Searching over {} trials", configs.len());
+
+    let rounds = search.rounds.max(1);
+    let mut alive: Vec<usize> = (0..configs.len()).collect();
+    let mut results: Vec<Option<TrialResult>> = vec![None; configs.len()];
+    for round in 0..rounds {
+        let budget_divisor = 1 << (rounds - 1 - round);
+        let mut round_results: Vec<TrialResult> = alive
+            .par_iter()
+            .map(|&trial| {
+                let config = &configs[trial];
+                let n_epoch = (config.n_epoch / budget_divisor).max(1);
+                run_trial(trial, config, round, n_epoch, search)
+            })
+            .collect();
+        round_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        println!(
+            "Round {}: best score {} over {} trials",
+            round,
+            round_results[0].score,
+            round_results.len()
+        );
+        alive = round_results
+            .iter()
+            .take(round_results.len().div_ceil(2))
+            .map(|result| result.trial)
+            .collect();
+        for result in round_results {
+            let trial = result.trial;
+            results[trial] = Some(result);
+        }
+    }
+
+    // Trials of later rounds first, then by score
+    let mut results: Vec<TrialResult> = results.into_iter().flatten().collect();
+    results.sort_by(|a, b| b.round.cmp(&a.round).then(b.score.total_cmp(&a.score)));
+    Ok(SearchSummary {
+        baseline: format!("{:?}", search.baseline),
+        eval_games: search.eval_games,
+        results,
+    })
+}
+
+impl fmt::Display for SearchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>4} {:>6} {:>6} {:>7} {:>7} {:>7} {:>7}  settings",
+            "Rank", "Trial", "Round", "Epochs", "Score", "Losses", "Seconds"
+        )?;
+        for (rank, result) in self.results.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4} {:>6} {:>6} {:>7} {:>7.3} {:>7} {:>7.1}  {:?}",
+                rank + 1,
+                result.trial,
+                result.round,
+                result.n_epoch,
+                result.score,
+                result.losses,
+                result.seconds,
+                result.config
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_covers_every_combination() {
+        let space = SearchSpace {
+            alpha: vec![vec![0.5], vec![0.9, 0.1]],
+            rewards: vec![
+                Rewards::default(),
+                Rewards {
+                    win: 1.0,
+                    draw: 0.0,
+                    loss: -1.0,
+                },
+            ],
+            n_epoch: vec![10, 20, 30],
+            ..SearchSpace::default()
+        };
+        let grid = space.grid();
+        assert_eq!(grid.len(), 12);
+        assert_eq!(grid.iter().filter(|config| config.n_epoch == 20).count(), 4);
+    }
+
+    #[test]
+    fn test_successive_halving_keeps_best_half() {
+        let space = SearchSpace {
+            alpha: vec![vec![0.9, 0.1], vec![0.5]],
+            epsilon: vec![vec![0.5, 0.01], vec![0.3]],
+            n_epoch: vec![4],
+            n_games: vec![50],
+            ..SearchSpace::default()
+        };
+        let search = SearchConfig {
+            strategy: Strategy::Grid,
+            rounds: 2,
+            baseline: OpponentKind::Random,
+            eval_games: 20,
+            board: BoardConfig::default(),
+            options: TrainingOptions::default(),
+            seed: 0,
+        };
+        let summary = run_search(&space, &search).unwrap();
+        assert_eq!(summary.results.len(), 4);
+        assert_eq!(summary.results.iter().filter(|r| r.round == 1).count(), 2);
+        assert!(summary.results[..2].iter().all(|r| r.n_epoch == 4));
+        assert!(summary.results[2..].iter().all(|r| r.n_epoch == 2));
+
+        let empty_grid = SearchSpace {
+            alpha: vec![vec![]],
+            ..space.clone()
+        };
+        assert!(run_search(&empty_grid, &search).is_err());
+        let too_many_rounds = SearchConfig {
+            rounds: 64,
+            ..search
+        };
+        assert!(run_search(&space, &too_many_rounds).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{collections::HashSet, fmt, mem};
+
+use crate::{
+    agent::{Entry, QTable, State, StateAction},
+    enumeration::reachable_states,
+    logic::{BoardConfig, CellState},
+};
+
+/// Number of bins of the value histogram
+const N_BINS: usize = 10;
+/// Largest number of positions enumerated to compute the coverage
+const MAX_ENUMERATED_STATES: usize = 1_000_000;
+
+/// Summary of the content of a Q-table, to tell unexplored states from bad values
+pub struct TableStats {
+    pub n_states: usize,
+    pub n_entries: usize,
+    /// Entries updated a single time
+    pub n_single_visit: usize,
+    pub mean_visits: f64,
+    /// Range of the epochs of the last updates
+    pub last_updates: Option<(usize, usize)>,
+    /// Number of reachable non terminal positions with at least one entry, and number of
+    /// reachable non terminal positions, missing when the board is too large to enumerate
+    pub coverage: Option<(usize, usize)>,
+    /// Lower bound, upper bound and number of Q-values of every bin
+    pub histogram: Vec<(f64, f64, usize)>,
+    /// Approximate heap and table memory used by the entries, in bytes
+    pub memory: usize,
+}
+
+impl TableStats {
+    pub fn new(table: &QTable, board: BoardConfig) -> Self {
+        let entries: Vec<(&State, &Entry)> = table
+            .entries()
+            .map(|(state, _, entry)| (state, entry))
+            .collect();
+        let states: HashSet<&State> = entries.iter().map(|&(state, _)| state).collect();
+        let n_entries = entries.len();
+        let total_visits: usize = entries.iter().map(|(_, entry)| entry.visits).sum();
+        let last_updates = entries.iter().map(|(_, entry)| entry.last_update).fold(
+            None,
+            |range: Option<(usize, usize)>, epoch| {
+                Some(range.map_or((epoch, epoch), |(first, last)| {
+                    (first.min(epoch), last.max(epoch))
+                }))
+            },
+        );
+        let coverage = reachable_states(board, MAX_ENUMERATED_STATES).map(|reachable| {
+            let covered = reachable
+                .iter()
+                .filter(|state| states.contains(state))
+                .count();
+            (covered, reachable.len())
+        });
+
+        let q_values: Vec<f64> = entries.iter().map(|(_, entry)| entry.q).collect();
+        let min = q_values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = q_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let width = ((max - min) / N_BINS as f64).max(f64::EPSILON);
+        let mut histogram: Vec<(f64, f64, usize)> = (0..N_BINS)
+            .map(|bin| (min + bin as f64 * width, min + (bin + 1) as f64 * width, 0))
+            .collect();
+        if !q_values.is_empty() {
+            for q in &q_values {
+                let bin = (((q - min) / width) as usize).min(N_BINS - 1);
+                histogram[bin].2 += 1;
+            }
+        }
+
+        // Every entry owns its grid, a vector of columns, plus one control byte in the table
+        let grid_bytes = board.columns * mem::size_of::<Vec<CellState>>()
+            + board.n_cells() * mem::size_of::<CellState>();
+        let entry_bytes = mem::size_of::<(StateAction, Entry)>() + 1 + grid_bytes;
+
+        Self {
+            n_states: states.len(),
+            n_entries,
+            n_single_visit: entries
+                .iter()
+                .filter(|(_, entry)| entry.visits == 1)
+                .count(),
+            mean_visits: total_visits as f64 / n_entries.max(1) as f64,
+            last_updates,
+            coverage,
+            histogram: if q_values.is_empty() {
+                Vec::new()
+            } else {
+                histogram
+            },
+            memory: n_entries * entry_bytes,
+        }
+    }
+}
+
+impl fmt::Display for TableStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "States: {}", self.n_states)?;
+        writeln!(f, "Entries: {}", self.n_entries)?;
+        writeln!(
+            f,
+            "Visits: {:.1} on average, {} entries updated once",
+            self.mean_visits, self.n_single_visit
+        )?;
+        if let Some((first, last)) = self.last_updates {
+            writeln!(f, "Last updates: epochs {} to {}", first, last)?;
+        }
+        match self.coverage {
+            Some((covered, reachable)) => writeln!(
+                f,
+                "Coverage: {} of {} reachable positions ({:.1}%)",
+                covered,
+                reachable,
+                100.0 * covered as f64 / reachable.max(1) as f64
+            )?,
+            None => writeln!(f, "Coverage: board too large to enumerate")?,
+        }
+        writeln!(
+            f,
+            "Memory: {:.1} MiB",
+            self.memory as f64 / (1 << 20) as f64
+        )?;
+        writeln!(f, "Q-values:")?;
+        let largest = self.histogram.iter().map(|&(_, _, n)| n).max().unwrap_or(0);
+        for &(lower, upper, count) in &self.histogram {
+            let bar = "#".repeat((40 * count).div_ceil(largest.max(1)));
+            writeln!(f, "  [{:+.3}, {:+.3}) {:>8} {}", lower, upper, count, bar)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{train, TrainingOptions};
+
+    #[test]
+    fn test_stats_of_trained_table() {
+        let options = TrainingOptions {
+            seed: Some(1),
+            ..TrainingOptions::default()
+        };
+        let table = train(50, 3, BoardConfig::default(), &options);
+        let stats = TableStats::new(&table, BoardConfig::default());
+        assert!(stats.n_states > 0 && stats.n_entries >= stats.n_states);
+        let (covered, reachable) = stats.coverage.unwrap();
+        assert_eq!(reachable, 4520);
+        assert!(covered > 0 && covered <= stats.n_states);
+        let binned: usize = stats.histogram.iter().map(|&(_, _, n)| n).sum();
+        assert_eq!(binned, stats.n_entries);
+        assert_eq!(stats.last_updates, Some((0, 2)));
+    }
+}
@@ -0,0 +1,390 @@
+use anyhow::{bail, Context};
+use rand::{distributions::WeightedIndex, prelude::*};
+use serde::Serialize;
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use crate::{
+    agent::{Agent, AgentSpec},
+    evaluation::{evaluate, EvaluationResult},
+    logic::{Board, BoardConfig},
+    mcts::Budget,
+    policy::Policy,
+};
+
+/// Number of bootstrap resamples used for the rating uncertainty
+const N_BOOTSTRAP: usize = 200;
+/// Draws added to every pairing so agents that never score keep a finite rating
+const PRIOR_DRAWS: f64 = 1.0;
+
+/// Agent entered in a tournament
+#[derive(Clone, Debug)]
+pub struct Participant {
+    pub name: String,
+    pub spec: AgentSpec,
+}
+
+impl FromStr for Participant {
+    type Err = anyhow::Error;
+
+    /// Parses `random`, `heuristic`, `minimax:<depth>`, `mcts:<iterations>` or `table:<path>`
+    /// for a saved Q-table playing greedily, optionally prefixed by `<name>=`
+    fn from_str(entry: &str) -> anyhow::Result<Self> {
+        let (name, kind) = match entry.split_once('=') {
+            Some((name, kind)) => (Some(name.to_string()), kind),
+            None => (None, entry),
+        };
+        let parse_number = |value: &str| -> anyhow::Result<usize> {
+            value
+                .parse()
+                .with_context(|| format!("Invalid number in participant `{}`", entry))
+        };
+        let spec = match kind.split_once(':') {
+            None if kind == "random" => AgentSpec::Random,
+            None if kind == "heuristic" => AgentSpec::Heuristic,
+            Some(("minimax", depth)) => AgentSpec::Minimax {
+                depth: parse_number(depth)?,
+            },
+            Some(("mcts", iterations)) => AgentSpec::Mcts {
+                budget: Budget::Iterations(parse_number(iterations)?),
+                heuristic_rollouts: false,
+            },
+            Some(("table", path)) => AgentSpec::SavedQTable {
+                path: path.into(),
+                policy: Policy::Greedy,
+            },
+            _ => bail!("Unknown participant `{}`", entry),
+        };
+        let name = name.unwrap_or_else(|| match kind.split_once(':') {
+            Some(("table", path)) => Path::new(path)
+                .file_stem()
+                .map_or(kind.to_string(), |stem| stem.to_string_lossy().into_owned()),
+            _ => kind.to_string(),
+        });
+        Ok(Self { name, spec })
+    }
+}
+
+/// Rating of a participant on the Elo scale, centred on 1500
+#[derive(Clone, Debug, Serialize)]
+pub struct Rating {
+    pub name: String,
+    pub elo: f64,
+    /// Standard deviation of the rating over bootstrap resamples of the games
+    pub elo_std: f64,
+}
+
+/// Outcome of a round robin
+#[derive(Clone, Debug, Serialize)]
+pub struct Tournament {
+    pub names: Vec<String>,
+    /// Results of every row participant against every column one, both colours alternated
+    pub results: Vec<Vec<Option<Results>>>,
+    pub ratings: Vec<Rating>,
+}
+
+/// Serializable copy of an `EvaluationResult`
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Results {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl From<EvaluationResult> for Results {
+    fn from(result: EvaluationResult) -> Self {
+        Self {
+            wins: result.wins,
+            draws: result.draws,
+            losses: result.losses,
+        }
+    }
+}
+
+impl Results {
+    fn score(&self) -> f64 {
+        EvaluationResult {
+            wins: self.wins,
+            draws: self.draws,
+            losses: self.losses,
+        }
+        .score()
+    }
+}
+
+/// Plays every pair of participants `n_games` times from the `start` position, swapping colours
+/// after every game, and rates them with a Bradley–Terry model where a draw counts as half a win
+pub fn run_tournament(
+    participants: &[Participant],
+    n_games: usize,
+    board: BoardConfig,
+    start: Option<&str>,
+    rng: &mut dyn RngCore,
+) -> anyhow::Result<Tournament> {
+    let start_board = Board::from_start(start, board)?;
+    let mut agents: Vec<Box<dyn Agent>> = participants
+        .iter()
+        .map(|participant| {
+            participant
+                .spec
+                .clone()
+                .with_start(start.map(str::to_string))
+                .build(board, rng)
+                .with_context(|| format!("Cannot build participant {}", participant.name))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let n = participants.len();
+    let mut results = vec![vec![None; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let (left, right) = agents.split_at_mut(j);
+            let result = evaluate(
+                left[i].as_mut(),
+                right[0].as_mut(),
+                n_games,
+                &start_board,
+                rng,
+            );
+            println!(
+                "{} vs {}: {} wins, {} draws, {} losses",
+                participants[i].name,
+                participants[j].name,
+                result.wins,
+                result.draws,
+                result.losses
+            );
+            results[i][j] = Some(Results::from(result));
+            results[j][i] = Some(Results {
+                wins: result.losses,
+                draws: result.draws,
+                losses: result.wins,
+            });
+        }
+    }
+
+    let elo = bradley_terry(&results);
+    // Resamples the games of every pairing to measure how much the ratings depend on luck
+    let mut samples = vec![Vec::with_capacity(N_BOOTSTRAP); n];
+    for _ in 0..N_BOOTSTRAP {
+        let mut resampled = results.clone();
+        for i in 0..n {
+            for j in i + 1..n {
+                let Some(result) = results[i][j] else {
+                    continue;
+                };
+                let outcomes = WeightedIndex::new([result.wins, result.draws, result.losses]);
+                let mut sample = Results {
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                };
+                if let Ok(outcomes) = outcomes {
+                    for _ in 0..result.wins + result.draws + result.losses {
+                        match outcomes.sample(rng) {
+                            0 => sample.wins += 1,
+                            1 => sample.draws += 1,
+                            _ => sample.losses += 1,
+                        }
+                    }
+                }
+                resampled[i][j] = Some(sample);
+                resampled[j][i] = Some(Results {
+                    wins: sample.losses,
+                    draws: sample.draws,
+                    losses: sample.wins,
+                });
+            }
+        }
+        for (index, rating) in bradley_terry(&resampled).into_iter().enumerate() {
+            samples[index].push(rating);
+        }
+    }
+
+    let ratings = participants
+        .iter()
+        .zip(elo)
+        .zip(samples)
+        .map(|((participant, elo), samples)| {
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let variance =
+                samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+            Rating {
+                name: participant.name.clone(),
+                elo,
+                elo_std: variance.sqrt(),
+            }
+        })
+        .collect();
+    Ok(Tournament {
+        names: participants.iter().map(|p| p.name.clone()).collect(),
+        results,
+        ratings,
+    })
+}
+
+/// Fits Bradley–Terry strengths with the minorization-maximization updates and returns them on
+/// the Elo scale, centred on 1500
+fn bradley_terry(results: &[Vec<Option<Results>>]) -> Vec<f64> {
+    let n = results.len();
+    let games = |i: usize, j: usize| {
+        results[i][j].map_or(0.0, |r| (r.wins + r.draws + r.losses) as f64 + PRIOR_DRAWS)
+    };
+    let points = |i: usize, j: usize| {
+        results[i][j].map_or(0.0, |r| {
+            r.wins as f64 + 0.5 * r.draws as f64 + 0.5 * PRIOR_DRAWS
+        })
+    };
+    let mut strengths = vec![1.0; n];
+    for _ in 0..1000 {
+        let mut next: Vec<f64> = (0..n)
+            .map(|i| {
+                let total_points: f64 = (0..n).map(|j| points(i, j)).sum();
+                let denominator: f64 = (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| games(i, j) / (strengths[i] + strengths[j]))
+                    .sum();
+                if denominator > 0.0 {
+                    total_points / denominator
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        // Strengths are only defined up to a factor, their geometric mean is kept at 1
+        let log_mean = next.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+        next.iter_mut().for_each(|s| *s /= log_mean.exp());
+        let change = next
+            .iter()
+            .zip(&strengths)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        strengths = next;
+        if change < 1e-10 {
+            break;
+        }
+    }
+    strengths
+        .into_iter()
+        .map(|s| 1500.0 + 400.0 * s.log10())
+        .collect()
+}
+
+impl Tournament {
+    /// Writes the ratings and the cross-table, as JSON when the extension is `json` and CSV
+    /// otherwise
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        let content = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::to_string_pretty(self)?
+        } else {
+            self.to_csv()
+        };
+        fs::write(path, content).with_context(|| format!("Cannot write {}", path.display()))
+    }
+
+    /// One row per participant with its rating and its score against every participant
+    fn to_csv(&self) -> String {
+        let names: Vec<String> = self.names.iter().map(|name| csv_field(name)).collect();
+        let mut csv = format!("agent,elo,elo_std,{}\n", names.join(","));
+        for (rating, row) in self.ratings.iter().zip(&self.results) {
+            let scores: Vec<String> = row
+                .iter()
+                .map(|result| result.map_or(String::new(), |r| r.score().to_string()))
+                .collect();
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&rating.name),
+                rating.elo,
+                rating.elo_std,
+                scores.join(",")
+            ));
+        }
+        csv
+    }
+}
+
+/// Quotes the field when it holds a separator, a quote or a line break, doubling its quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl fmt::Display for Tournament {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        write!(f, "{:width$} {:>7} {:>6} |", "", "Elo", "+/-")?;
+        for name in &self.names {
+            write!(f, " {:>width$}", name)?;
+        }
+        writeln!(f)?;
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| self.ratings[b].elo.total_cmp(&self.ratings[a].elo));
+        for i in order {
+            let rating = &self.ratings[i];
+            write!(
+                f,
+                "{:width$} {:>7.0} {:>6.0} |",
+                rating.name, rating.elo, rating.elo_std
+            )?;
+            for result in &self.results[i] {
+                match result {
+                    Some(result) => write!(f, " {:>width$.3}", result.score())?,
+                    None => write!(f, " {:>width$}", "-")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_parse_participant() {
+        let participant: Participant = "table:runs/league.json".parse().unwrap();
+        assert_eq!(participant.name, "league");
+        let participant: Participant = "deep=minimax:9".parse().unwrap();
+        assert_eq!(participant.name, "deep");
+        assert!(matches!(participant.spec, AgentSpec::Minimax { depth: 9 }));
+        assert!("alphazero".parse::<Participant>().is_err());
+    }
+
+    #[test]
+    fn test_ratings_follow_strength() {
+        let participants: Vec<Participant> = ["random", "heuristic", "minimax:9"]
+            .iter()
+            .map(|entry| entry.parse().unwrap())
+            .collect();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let tournament =
+            run_tournament(&participants, 40, BoardConfig::default(), None, &mut rng).unwrap();
+        let elo: Vec<f64> = tournament.ratings.iter().map(|r| r.elo).collect();
+        assert!(elo[0] < elo[1] && elo[1] < elo[2]);
+        let score = |i: usize, j: usize| tournament.results[i][j].unwrap().score();
+        assert!((score(0, 2) + score(2, 0) - 1.0).abs() < 1e-12);
+        assert!(tournament.ratings.iter().all(|r| r.elo_std > 0.0));
+        assert_eq!(tournament.to_csv().lines().count(), 4);
+    }
+
+    #[test]
+    fn test_csv_quotes_names() {
+        assert_eq!(csv_field("minimax"), "minimax");
+        assert_eq!(csv_field("runs/a,b.json"), "\"runs/a,b.json\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
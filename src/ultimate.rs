@@ -0,0 +1,248 @@
+use rand::RngCore;
+
+use crate::{
+    agent::{train_self_play, Action, Game, QTable, State, TrainingOptions},
+    evaluation::{evaluate_against_random, EvaluationResult},
+    logic::{Board, BoardConfig, CellState},
+};
+
+/// Number of sub-boards per side of the meta board, and of cells per side of a sub-board
+pub const SIZE: usize = 3;
+
+/// Ultimate tic-tac-toe: every cell of the 3x3 meta board is a tic-tac-toe board, and the cell
+/// played in a sub-board sends the opponent to the matching sub-board, or anywhere when that
+/// sub-board is already won or full. Moves use the coordinates of the 9x9 grid of cells.
+#[derive(Clone, Debug)]
+pub struct UltimateBoard {
+    boards: Vec<Vec<Board>>,
+    turn: CellState,
+    /// Sub-board the player to move has to play in, any open one when `None`
+    active: Option<(usize, usize)>,
+}
+
+impl Default for UltimateBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UltimateBoard {
+    pub fn new() -> Self {
+        Self {
+            boards: vec![vec![Board::new(BoardConfig::default()); SIZE]; SIZE],
+            turn: CellState::X,
+            active: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Sub-board the player to move has to play in, any open one when `None`
+    pub fn active_board(&self) -> Option<(usize, usize)> {
+        self.active
+    }
+
+    /// Winner of a sub-board, which then counts as a cell of that player on the meta board
+    pub fn sub_board_winner(&self, sub_x: usize, sub_y: usize) -> Option<CellState> {
+        self.boards[sub_x][sub_y].is_winner()
+    }
+
+    /// Returns whether moves can still be played in the sub-board
+    fn is_open(&self, sub_x: usize, sub_y: usize) -> bool {
+        let board = &self.boards[sub_x][sub_y];
+        board.is_winner().is_none() && !board.is_board_full()
+    }
+
+    pub fn is_valid_move(&self, x_axis: usize, y_axis: usize) -> bool {
+        if x_axis >= SIZE * SIZE || y_axis >= SIZE * SIZE {
+            return false;
+        }
+        let sub = (x_axis / SIZE, y_axis / SIZE);
+        self.active.is_none_or(|active| active == sub)
+            && self.is_open(sub.0, sub.1)
+            && self.boards[sub.0][sub.1].is_valid_move(x_axis % SIZE, y_axis % SIZE)
+    }
+
+    /// Plays the move for the player to move and sends the opponent to the sub-board matching
+    /// the cell played
+    pub fn play_move(&mut self, x_axis: usize, y_axis: usize) {
+        if !self.is_valid_move(x_axis, y_axis) {
+            return;
+        }
+        let board = &mut self.boards[x_axis / SIZE][y_axis / SIZE];
+        board.set_current_player(self.turn);
+        board.play_move(x_axis % SIZE, y_axis % SIZE);
+        let next = (x_axis % SIZE, y_axis % SIZE);
+        self.active = self.is_open(next.0, next.1).then_some(next);
+        self.turn = self.turn.opponent();
+    }
+
+    pub fn get_possible_actions(&self) -> Vec<(usize, usize)> {
+        (0..SIZE * SIZE)
+            .flat_map(|x_axis| (0..SIZE * SIZE).map(move |y_axis| (x_axis, y_axis)))
+            .filter(|&(x_axis, y_axis)| self.is_valid_move(x_axis, y_axis))
+            .collect()
+    }
+
+    /// Returns the player owning a line of sub-boards if there's any
+    pub fn is_winner(&self) -> Option<CellState> {
+        let meta = (0..SIZE)
+            .map(|sub_x| {
+                (0..SIZE)
+                    .map(|sub_y| {
+                        self.sub_board_winner(sub_x, sub_y)
+                            .unwrap_or(CellState::Empty)
+                    })
+                    .collect()
+            })
+            .collect();
+        Board::from_grid(meta, BoardConfig::default()).is_winner()
+    }
+
+    /// Returns whether every sub-board is won or full, so no move is left
+    pub fn is_board_full(&self) -> bool {
+        (0..SIZE).all(|sub_x| (0..SIZE).all(|sub_y| !self.is_open(sub_x, sub_y)))
+    }
+
+    pub fn get_current_player(&self) -> CellState {
+        self.turn
+    }
+
+    /// Cells of the 9x9 grid, indexed by x then y like a `Board`
+    pub fn get_grid(&self) -> State {
+        let grids: Vec<Vec<State>> = self
+            .boards
+            .iter()
+            .map(|column| column.iter().map(Board::get_grid).collect())
+            .collect();
+        (0..SIZE * SIZE)
+            .map(|x_axis| {
+                (0..SIZE * SIZE)
+                    .map(|y_axis| grids[x_axis / SIZE][y_axis / SIZE][x_axis % SIZE][y_axis % SIZE])
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Game for UltimateBoard {
+    fn reset(&mut self) {
+        UltimateBoard::reset(self);
+    }
+
+    fn play(&mut self, action: Action) {
+        self.play_move(action.x_axis, action.y_axis);
+    }
+
+    fn winner(&self) -> Option<CellState> {
+        self.is_winner()
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_board_full()
+    }
+
+    fn possible_actions(&self) -> Vec<Action> {
+        self.get_possible_actions()
+            .into_iter()
+            .map(|(x_axis, y_axis)| Action {
+                x_axis,
+                y_axis,
+                layer: 0,
+                symbol: None,
+            })
+            .collect()
+    }
+
+    /// The grid followed by a column flagging the active sub-board, since the same cells can be
+    /// reached with different sub-boards to play in
+    fn state(&self) -> State {
+        let mut state = self.get_grid();
+        let mut active = vec![CellState::Empty; SIZE * SIZE];
+        if let Some((sub_x, sub_y)) = self.active {
+            active[sub_x * SIZE + sub_y] = CellState::X;
+        }
+        state.push(active);
+        state
+    }
+
+    fn current_player(&self) -> CellState {
+        self.turn
+    }
+}
+
+/// Trains a table by self-play, see `train_self_play`
+#[allow(dead_code)]
+pub fn train(n_games: usize, n_epoch: usize, options: &TrainingOptions) -> anyhow::Result<QTable> {
+    train_self_play(UltimateBoard::new(), n_games, n_epoch, options)
+}
+
+/// Greedy move of the table
+pub fn select_action(table: &QTable, board: &UltimateBoard, rng: &mut dyn RngCore) -> Action {
+    table.greedy_action(board, rng)
+}
+
+/// Plays `n_games` of the greedy table against a uniformly random player, swapping sides after
+/// every game
+#[allow(dead_code)]
+pub fn evaluate(table: &QTable, n_games: usize, rng: &mut dyn RngCore) -> EvaluationResult {
+    evaluate_against_random(table, UltimateBoard::new(), n_games, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convergence::EarlyStopping;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_move_sends_opponent_to_matching_sub_board() {
+        let mut board = UltimateBoard::new();
+        assert_eq!(board.get_possible_actions().len(), 81);
+        board.play_move(4, 5);
+        assert_eq!(board.active_board(), Some((1, 2)));
+        assert!(!board.is_valid_move(0, 0));
+        assert!(board.is_valid_move(3, 6));
+        assert_eq!(board.get_possible_actions().len(), 9);
+        assert_eq!(board.get_grid()[4][5], CellState::X);
+    }
+
+    #[test]
+    fn test_closed_sub_board_frees_the_next_move() {
+        let config = BoardConfig::default();
+        let mut board = UltimateBoard::new();
+        board.boards[0][0] = Board::from_position("XXX/OO./...", config).unwrap();
+        board.play_move(3, 3);
+        assert_eq!(board.active_board(), None);
+        assert!(!board.is_valid_move(2, 2));
+        assert_eq!(board.get_possible_actions().len(), 81 - 9 - 1);
+        assert_eq!(board.sub_board_winner(0, 0), Some(CellState::X));
+        assert_eq!(board.is_winner(), None);
+
+        board.boards[1][0] = board.boards[0][0].clone();
+        board.boards[2][0] = board.boards[0][0].clone();
+        assert_eq!(board.is_winner(), Some(CellState::X));
+    }
+
+    #[test]
+    fn test_trained_table_plays_full_games() {
+        let options = TrainingOptions {
+            seed: Some(0),
+            quiet: true,
+            ..TrainingOptions::default()
+        };
+        let table = train(50, 2, &options).unwrap();
+        assert!(table.entries().count() > 0);
+        let result = evaluate(&table, 20, &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!(result.n_games(), 20);
+
+        let options = TrainingOptions {
+            early_stopping: Some(EarlyStopping::default()),
+            ..options
+        };
+        assert!(train(50, 2, &options).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    agent::{Action, Game, State},
+    logic::{Board, BoardConfig, CellState, Variant},
+};
+
+/// Symbols either player can place
+const SYMBOLS: [CellState; 2] = [CellState::X, CellState::O];
+
+/// Wild tic-tac-toe: each player chooses whether to place an X or an O, and whoever completes a
+/// line of either symbol wins, or loses in the misère variant. The players are still called X,
+/// who starts, and O.
+#[derive(Clone, Debug)]
+pub struct WildBoard {
+    /// Symbols on the grid, the rules of its config besides the variant apply
+    board: Board,
+    variant: Variant,
+    turn: CellState,
+    winner: Option<CellState>,
+}
+
+impl WildBoard {
+    pub fn new(config: BoardConfig) -> Self {
+        Self {
+            // The board only finds lines, they are credited to the player who completed them
+            board: Board::new(BoardConfig {
+                variant: Variant::Standard,
+                ..config
+            }),
+            variant: config.variant,
+            turn: CellState::X,
+            winner: None,
+        }
+    }
+
+    pub fn config(&self) -> BoardConfig {
+        BoardConfig {
+            variant: self.variant,
+            ..self.board.config()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.board.reset();
+        self.turn = CellState::X;
+        self.winner = None;
+    }
+
+    pub fn is_valid_move(&self, x_axis: usize, y_axis: usize) -> bool {
+        self.winner.is_none() && self.board.is_valid_move(x_axis, y_axis)
+    }
+
+    /// Cell where a move at x_axis, y_axis puts the symbol, see `Board::landing_cell`
+    pub fn landing_cell(&self, x_axis: usize, y_axis: usize) -> (usize, usize) {
+        self.board.landing_cell(x_axis, y_axis)
+    }
+
+    /// Places the symbol for the player to move
+    pub fn play_move(&mut self, x_axis: usize, y_axis: usize, symbol: CellState) {
+        if !self.is_valid_move(x_axis, y_axis) {
+            return;
+        }
+        self.board.set_current_player(symbol);
+        self.board.play_move(x_axis, y_axis);
+        if self.board.is_winner().is_some() {
+            self.winner = Some(match self.variant {
+                Variant::Standard => self.turn,
+                Variant::Misere => self.turn.opponent(),
+            });
+        }
+        self.turn = self.turn.opponent();
+    }
+
+    /// Returns the player credited with the first completed line, if any
+    pub fn is_winner(&self) -> Option<CellState> {
+        self.winner
+    }
+
+    pub fn is_board_full(&self) -> bool {
+        self.board.is_board_full()
+    }
+
+    /// Every legal cell with both symbols, twice the moves of the board
+    pub fn get_possible_actions(&self) -> Vec<Action> {
+        if self.winner.is_some() {
+            return Vec::new();
+        }
+        self.board
+            .get_possible_actions()
+            .into_iter()
+            .flat_map(|(x_axis, y_axis)| {
+                SYMBOLS.map(|symbol| Action {
+                    x_axis,
+                    y_axis,
+                    layer: 0,
+                    symbol: Some(symbol),
+                })
+            })
+            .collect()
+    }
+
+    /// Symbols on the grid, the player to move follows from their number
+    pub fn get_grid(&self) -> State {
+        self.board.get_grid()
+    }
+}
+
+impl Game for WildBoard {
+    fn reset(&mut self) {
+        WildBoard::reset(self);
+    }
+
+    fn play(&mut self, action: Action) {
+        let symbol = action.symbol.unwrap_or(self.turn);
+        self.play_move(action.x_axis, action.y_axis, symbol);
+    }
+
+    fn winner(&self) -> Option<CellState> {
+        self.is_winner()
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_board_full()
+    }
+
+    fn possible_actions(&self) -> Vec<Action> {
+        self.get_possible_actions()
+    }
+
+    fn state(&self) -> State {
+        self.get_grid()
+    }
+
+    fn current_player(&self) -> CellState {
+        self.turn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_is_credited_to_the_player_completing_it() {
+        let mut wild = WildBoard::new(BoardConfig::default());
+        assert_eq!(wild.get_possible_actions().len(), 18);
+        wild.play_move(0, 0, CellState::O);
+        wild.play_move(1, 1, CellState::X);
+        wild.play_move(1, 0, CellState::O);
+        assert_eq!(wild.is_winner(), None);
+        // Player O completes the line of O symbols started by player X
+        wild.play_move(2, 0, CellState::O);
+        assert_eq!(wild.is_winner(), Some(CellState::O));
+        assert!(wild.get_possible_actions().is_empty());
+    }
+
+    #[test]
+    fn test_misere_line_is_lost_by_the_player_completing_it() {
+        let config = BoardConfig {
+            variant: Variant::Misere,
+            ..BoardConfig::default()
+        };
+        let mut wild = WildBoard::new(config);
+        for (x_axis, y_axis) in [(0, 0), (1, 0), (2, 0)] {
+            wild.play(Action {
+                x_axis,
+                y_axis,
+                layer: 0,
+                symbol: Some(CellState::X),
+            });
+        }
+        assert_eq!(wild.is_winner(), Some(CellState::O));
+        assert_eq!(wild.config(), config);
+    }
+}